@@ -0,0 +1,373 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use futures::{future::select_all, stream::FuturesUnordered, StreamExt};
+use rand::{distributions::Alphanumeric, Rng};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::{Host, Url};
+
+use crate::error::AppError;
+
+const CALLBACK_PATH: &str = "/callback";
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+// a connection sending no complete request in time is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+const STATE_LENGTH: usize = 32;
+
+const SUCCESS_PAGE: &str = "<html><body><h3>Login successful</h3><p>You can close this window and return to DCAdmin.</p></body></html>";
+const FAILURE_PAGE: &str =
+    "<html><body><h3>Login failed</h3><p>Please return to DCAdmin and try again.</p></body></html>";
+
+/// Listens on a loopback port for the OAuth redirect
+pub struct CallbackListener {
+    // `localhost` is bound on IPv4 and IPv6 - browsers might resolve it to either
+    listeners: Vec<TcpListener>,
+    redirect_uri: String,
+    path: String,
+}
+
+impl CallbackListener {
+    /// Binds a random port
+    pub async fn bind() -> Result<Self, AppError> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.map_err(|e| {
+            tracing::error!("Error binding loopback listener: {e}");
+            AppError::from(e)
+        })?;
+
+        let port = listener
+            .local_addr()
+            .map_err(|e| {
+                tracing::error!("Error reading loopback listener address: {e}");
+                AppError::from(e)
+            })?
+            .port();
+
+        Ok(Self {
+            listeners: vec![listener],
+            redirect_uri: format!("http://127.0.0.1:{port}{CALLBACK_PATH}"),
+            path: CALLBACK_PATH.to_string(),
+        })
+    }
+
+    /// Binds the port of a registered loopback redirect uri (e.g. http://localhost:8080/callback)
+    pub async fn bind_to(redirect_uri: &str) -> Result<Self, AppError> {
+        let url = Url::parse(redirect_uri).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+
+        let addresses: Vec<IpAddr> = match url.host() {
+            Some(Host::Domain("localhost")) => {
+                vec![Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()]
+            }
+            Some(Host::Ipv4(ip)) if ip.is_loopback() => vec![ip.into()],
+            Some(Host::Ipv6(ip)) if ip.is_loopback() => vec![ip.into()],
+            _ => Vec::new(),
+        };
+
+        let port = match url.port() {
+            Some(port) if url.scheme() == "http" && !addresses.is_empty() => port,
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "Not a loopback redirect uri: {redirect_uri}"
+                )))
+            }
+        };
+
+        let mut listeners = Vec::new();
+        let mut error = None;
+
+        for address in addresses {
+            match TcpListener::bind((address, port)).await {
+                Ok(listener) => listeners.push(listener),
+                Err(e) => {
+                    tracing::warn!("Error binding loopback listener on {address}:{port}: {e}");
+                    error = Some(e);
+                }
+            }
+        }
+
+        // one address is enough (e.g. IPv6 disabled)
+        if listeners.is_empty() {
+            tracing::error!("Error binding loopback listener on port {port}");
+            return Err(error.map_or_else(
+                || AppError::Internal("No loopback address".to_string()),
+                AppError::from,
+            ));
+        }
+
+        Ok(Self {
+            listeners,
+            redirect_uri: redirect_uri.to_string(),
+            path: url.path().to_string(),
+        })
+    }
+
+    pub fn redirect_uri(&self) -> String {
+        self.redirect_uri.clone()
+    }
+
+    /// Waits for the redirect and returns the auth code if the state matches
+    pub async fn wait_for_code(self, expected_state: &str) -> Result<String, AppError> {
+        self.wait_for_code_within(expected_state, CALLBACK_TIMEOUT)
+            .await
+    }
+
+    async fn wait_for_code_within(
+        self,
+        expected_state: &str,
+        timeout: Duration,
+    ) -> Result<String, AppError> {
+        tokio::time::timeout(timeout, self.accept_code(expected_state))
+            .await
+            .map_err(|_| {
+                tracing::warn!("Timed out waiting for OAuth redirect");
+                AppError::Internal("Timed out waiting for OAuth redirect".to_string())
+            })?
+    }
+
+    /// Handles connections concurrently until one carries the code or an error for the expected state
+    async fn accept_code(&self, expected_state: &str) -> Result<String, AppError> {
+        let mut connections = FuturesUnordered::new();
+
+        loop {
+            tokio::select! {
+                accepted = self.accept() => {
+                    connections.push(self.handle_connection(accepted?, expected_state));
+                }
+                Some(Some(result)) = connections.next(), if !connections.is_empty() => {
+                    return result;
+                }
+            }
+        }
+    }
+
+    async fn accept(&self) -> Result<TcpStream, AppError> {
+        let accepts = self
+            .listeners
+            .iter()
+            .map(|listener| Box::pin(listener.accept()));
+
+        let (accepted, _, _) = select_all(accepts).await;
+
+        accepted.map(|(stream, _)| stream).map_err(|e| {
+            tracing::error!("Error accepting loopback connection: {e}");
+            AppError::from(e)
+        })
+    }
+
+    /// Answers a single request - `None` if it is not the redirect for the expected state
+    async fn handle_connection(
+        &self,
+        mut stream: TcpStream,
+        expected_state: &str,
+    ) -> Option<Result<String, AppError>> {
+        let target =
+            match tokio::time::timeout(REQUEST_TIMEOUT, read_request_target(&mut stream)).await {
+                Ok(Some(target)) => target,
+                Ok(None) => {
+                    respond(&mut stream, "400 Bad Request", FAILURE_PAGE).await;
+                    return None;
+                }
+                Err(_) => {
+                    tracing::warn!("Timed out reading loopback request");
+                    return None;
+                }
+            };
+
+        let url = match Url::parse(&format!("http://127.0.0.1{target}")) {
+            Ok(url) if url.path() == self.path => url,
+            // browsers might request e.g. /favicon.ico - ignore these
+            _ => {
+                respond(&mut stream, "404 Not Found", "").await;
+                return None;
+            }
+        };
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+
+        // e.g. a stale tab of an earlier login - keep waiting for the current one
+        if param("state").as_deref() != Some(expected_state) {
+            tracing::warn!("Ignoring OAuth redirect with invalid state");
+            respond(&mut stream, "400 Bad Request", FAILURE_PAGE).await;
+            return None;
+        }
+
+        if let Some(error) = param("error") {
+            tracing::error!("Authorization failed: {error}");
+            respond(&mut stream, "400 Bad Request", FAILURE_PAGE).await;
+            return Some(Err(AppError::Internal(format!(
+                "Authorization failed: {error}"
+            ))));
+        }
+
+        match param("code") {
+            Some(code) => {
+                respond(&mut stream, "200 OK", SUCCESS_PAGE).await;
+                Some(Ok(code))
+            }
+            None => {
+                tracing::error!("No auth code in OAuth redirect");
+                respond(&mut stream, "400 Bad Request", FAILURE_PAGE).await;
+                Some(Err(AppError::InvalidInput(
+                    "No auth code provided".to_string(),
+                )))
+            }
+        }
+    }
+}
+
+pub fn generate_state() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(STATE_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+async fn read_request_target(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_SIZE {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut request_line = request.lines().next()?.split_whitespace();
+
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_string()),
+        _ => None,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        tracing::warn!("Error writing loopback response: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: &str = "expected-state";
+
+    /// Sends a GET request and returns the status line of the response
+    async fn get(port: u16, target: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let request = format!("GET {target} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    async fn listen() -> (u16, tokio::task::JoinHandle<Result<String, AppError>>) {
+        let listener = CallbackListener::bind().await.unwrap();
+        let port = Url::parse(&listener.redirect_uri())
+            .unwrap()
+            .port()
+            .unwrap();
+        let task = tokio::spawn(listener.wait_for_code_within(STATE, Duration::from_secs(5)));
+
+        (port, task)
+    }
+
+    #[tokio::test]
+    async fn test_returns_code() {
+        let (port, task) = listen().await;
+
+        let status = get(port, &format!("/callback?code=abc&state={STATE}")).await;
+
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(task.await.unwrap().unwrap(), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_ignores_invalid_state_and_other_paths() {
+        let (port, task) = listen().await;
+
+        assert_eq!(
+            get(port, "/callback?code=stale&state=other").await,
+            "HTTP/1.1 400 Bad Request"
+        );
+        assert_eq!(get(port, "/favicon.ico").await, "HTTP/1.1 404 Not Found");
+        assert!(!task.is_finished());
+
+        get(port, &format!("/callback?code=abc&state={STATE}")).await;
+        assert_eq!(task.await.unwrap().unwrap(), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_returns_authorization_error() {
+        let (port, task) = listen().await;
+
+        get(
+            port,
+            &format!("/callback?error=access_denied&state={STATE}"),
+        )
+        .await;
+
+        assert!(task.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_does_not_block() {
+        let (port, task) = listen().await;
+
+        // connected without sending a request
+        let _idle = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+        let status = get(port, &format!("/callback?code=abc&state={STATE}")).await;
+
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(task.await.unwrap().unwrap(), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_times_out() {
+        let listener = CallbackListener::bind().await.unwrap();
+
+        let result = listener
+            .wait_for_code_within(STATE, Duration::from_millis(50))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_binds_localhost() {
+        let listener = CallbackListener::bind_to("http://localhost:0/callback")
+            .await
+            .unwrap();
+
+        assert!(!listener.listeners.is_empty());
+        assert!(
+            CallbackListener::bind_to("http://example.com:8080/callback")
+                .await
+                .is_err()
+        );
+        assert!(CallbackListener::bind_to("https://localhost:8080/callback")
+            .await
+            .is_err());
+    }
+}
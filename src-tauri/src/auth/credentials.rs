@@ -1,33 +1,156 @@
+use std::{path::Path, sync::Arc};
+
 use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::{spawn_blocking, RwLock};
+
+use super::file_credentials::FileCredentialStore;
+use crate::error::AppError;
+
+const KEYRING_PROBE_USER: &str = "dcadmin-keyring-probe";
+
+pub type Credentials = Box<dyn HandleCredentials + Send + Sync>;
 
 pub trait HandleCredentials {
-    fn set_dracoon_env(&self, secret: &str) -> Result<(), String>;
-    fn get_dracoon_env(&self) -> Result<String, String>;
-    #[allow(unused)]
-    fn delete_dracoon_env(&self) -> Result<(), String>;
+    fn set_dracoon_env(&self, secret: &str) -> Result<(), AppError>;
+    fn get_dracoon_env(&self) -> Result<String, AppError>;
+    fn delete_dracoon_env(&self) -> Result<(), AppError>;
 }
 
 impl HandleCredentials for Entry {
-    fn set_dracoon_env(&self, secret: &str) -> Result<(), String> {
+    fn set_dracoon_env(&self, secret: &str) -> Result<(), AppError> {
         match self.set_password(secret) {
             Ok(()) => Ok(()),
-            Err(_) => Err("CredentialStorageFailed".to_string()),
+            Err(_) => Err(AppError::Credentials(
+                "Error storing credentials".to_string(),
+            )),
         }
     }
-    fn get_dracoon_env(&self) -> Result<String, String> {
+    fn get_dracoon_env(&self) -> Result<String, AppError> {
         match self.get_password() {
             Ok(pwd) => Ok(pwd),
-            Err(_) => Err("InvalidAccount".to_string()),
+            Err(_) => Err(AppError::Credentials("No stored credentials".to_string())),
         }
     }
-    fn delete_dracoon_env(&self) -> Result<(), String> {
+    fn delete_dracoon_env(&self) -> Result<(), AppError> {
         if self.get_password().is_err() {
-            return Err("InvalidAccount".to_string());
+            return Err(AppError::Credentials("No stored credentials".to_string()));
         }
 
         match self.delete_password() {
             Ok(()) => Ok(()),
-            Err(_) => Err("CredentialDeletionFailed".to_string()),
+            Err(_) => Err(AppError::Credentials(
+                "Error deleting credentials".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialBackend {
+    /// OS keyring if available, encrypted file otherwise
+    Auto,
+    Keyring,
+    File,
+}
+
+// `#[default]` on enum variants requires Rust 1.62
+impl Default for CredentialBackend {
+    fn default() -> Self {
+        CredentialBackend::Auto
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedCredentialStore {
+    pub backend: CredentialBackend,
+    pub resolved_backend: CredentialBackend,
+    pub keyring_available: bool,
+    pub is_unlocked: bool,
+    pub file_store_exists: bool,
+}
+
+/// Resolves where refresh tokens are stored
+pub struct CredentialManager {
+    backend: RwLock<CredentialBackend>,
+    keyring_available: bool,
+    file_store: RwLock<Option<Arc<FileCredentialStore>>>,
+}
+
+impl CredentialManager {
+    pub fn new(backend: CredentialBackend) -> Self {
+        let keyring_available = is_keyring_available();
+
+        CredentialManager {
+            backend: RwLock::new(backend),
+            keyring_available,
+            file_store: RwLock::new(None),
         }
     }
-}
\ No newline at end of file
+
+    pub fn keyring_available(&self) -> bool {
+        self.keyring_available
+    }
+
+    pub async fn backend(&self) -> CredentialBackend {
+        *self.backend.read().await
+    }
+
+    pub async fn set_backend(&self, backend: CredentialBackend) {
+        *self.backend.write().await = backend;
+    }
+
+    /// Backend actually used - never `Auto`
+    pub async fn resolved_backend(&self) -> CredentialBackend {
+        match self.backend().await {
+            CredentialBackend::Auto if self.keyring_available => CredentialBackend::Keyring,
+            CredentialBackend::Auto => CredentialBackend::File,
+            backend => backend,
+        }
+    }
+
+    pub async fn is_unlocked(&self) -> bool {
+        self.file_store.read().await.is_some()
+    }
+
+    pub async fn unlock(&self, config_dir: &Path, passphrase: &str) -> Result<(), AppError> {
+        let config_dir = config_dir.to_path_buf();
+        let passphrase = passphrase.to_string();
+
+        // key derivation is deliberately slow - keep it off the async runtime
+        let store = spawn_blocking(move || FileCredentialStore::unlock(&config_dir, &passphrase))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))??;
+        *self.file_store.write().await = Some(Arc::new(store));
+        Ok(())
+    }
+
+    pub async fn entry(&self, service: &str, user: &str) -> Result<Credentials, AppError> {
+        match self.resolved_backend().await {
+            CredentialBackend::File => {
+                let store = self.file_store.read().await;
+                let store = store.as_ref().ok_or_else(|| {
+                    tracing::warn!("Encrypted credential store is locked");
+                    AppError::CredentialStoreLocked
+                })?;
+                Ok(Box::new(store.entry(user)))
+            }
+            _ => {
+                let entry = Entry::new(service, user).map_err(|e| {
+                    tracing::error!("Error creating keyring entry: {e}");
+                    AppError::Credentials(e.to_string())
+                })?;
+                Ok(Box::new(entry))
+            }
+        }
+    }
+}
+
+fn is_keyring_available() -> bool {
+    match Entry::new(super::SERVICE_NAME, KEYRING_PROBE_USER) {
+        Ok(entry) => matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)),
+        Err(_) => false,
+    }
+}
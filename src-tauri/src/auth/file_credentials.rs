@@ -0,0 +1,396 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+
+use super::credentials::HandleCredentials;
+use crate::error::AppError;
+
+pub const CREDENTIALS_FILE: &str = "credentials.enc.json";
+const STORE_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+// encrypted with the derived key to verify the passphrase on unlock
+const CHECK_VALUE: &str = "dcadmin";
+// associated data of the check value - entries are bound to their user
+const CHECK_AAD: &str = "";
+
+#[derive(Serialize, Deserialize)]
+struct StoredCredentials {
+    version: u8,
+    salt: String,
+    check: EncryptedValue,
+    entries: HashMap<String, EncryptedValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedValue {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Credential file in the config directory encrypted with a key derived from a user passphrase
+pub struct FileCredentialStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    // serializes read-modify-write cycles on the file
+    lock: Mutex<()>,
+}
+
+impl FileCredentialStore {
+    /// Opens the store with the given passphrase - the store is created if it does not exist yet
+    pub fn unlock(config_dir: &Path, passphrase: &str) -> Result<Self, AppError> {
+        let path = config_dir.join(CREDENTIALS_FILE);
+
+        if !path.exists() {
+            let salt: [u8; SALT_LENGTH] = rand::random();
+            let cipher = derive_cipher(passphrase, &salt)?;
+
+            let stored = StoredCredentials {
+                version: STORE_VERSION,
+                salt: STANDARD.encode(salt),
+                check: encrypt(&cipher, CHECK_VALUE, CHECK_AAD)?,
+                entries: HashMap::new(),
+            };
+
+            let store = Self {
+                path,
+                cipher,
+                lock: Mutex::new(()),
+            };
+            store.write(&stored)?;
+
+            tracing::info!("Created encrypted credential store");
+            return Ok(store);
+        }
+
+        let stored = read(&path)?;
+        let salt = STANDARD.decode(&stored.salt).map_err(|e| {
+            tracing::error!("Invalid salt in credential store: {e}");
+            invalid_store()
+        })?;
+        let cipher = derive_cipher(passphrase, &salt)?;
+
+        if decrypt(&cipher, &stored.check, CHECK_AAD).as_deref() != Ok(CHECK_VALUE) {
+            tracing::error!("Invalid passphrase for credential store");
+            return Err(AppError::InvalidPassphrase);
+        }
+
+        Ok(Self {
+            path,
+            cipher,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn exists(config_dir: &Path) -> bool {
+        config_dir.join(CREDENTIALS_FILE).exists()
+    }
+
+    pub fn entry(self: &Arc<Self>, user: &str) -> FileCredentials {
+        FileCredentials {
+            store: self.clone(),
+            user: user.to_string(),
+        }
+    }
+
+    fn update<F>(&self, f: F) -> Result<(), AppError>
+    where
+        F: FnOnce(&mut HashMap<String, EncryptedValue>) -> Result<(), AppError>,
+    {
+        let _lock = self
+            .lock
+            .lock()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let mut stored = read(&self.path)?;
+        f(&mut stored.entries)?;
+        self.write(&stored)
+    }
+
+    fn write(&self, stored: &StoredCredentials) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(stored)?;
+
+        // write to a temporary file first to never leave a truncated store behind
+        let tmp_path = self.path.with_extension("tmp");
+        write_private(&tmp_path, &content)
+            .and_then(|_| std::fs::rename(&tmp_path, &self.path))
+            .map_err(|e| {
+                tracing::error!("Error writing credential store: {e}");
+                AppError::Credentials("Error storing credentials".to_string())
+            })
+    }
+}
+
+pub struct FileCredentials {
+    store: Arc<FileCredentialStore>,
+    user: String,
+}
+
+impl HandleCredentials for FileCredentials {
+    fn set_dracoon_env(&self, secret: &str) -> Result<(), AppError> {
+        let value = encrypt(&self.store.cipher, secret, &self.user)?;
+        self.store.update(|entries| {
+            entries.insert(self.user.clone(), value);
+            Ok(())
+        })
+    }
+
+    fn get_dracoon_env(&self) -> Result<String, AppError> {
+        let stored = read(&self.store.path)?;
+        let value = stored
+            .entries
+            .get(&self.user)
+            .ok_or_else(|| AppError::Credentials("No stored credentials".to_string()))?;
+
+        decrypt(&self.store.cipher, value, &self.user)
+    }
+
+    fn delete_dracoon_env(&self) -> Result<(), AppError> {
+        self.store.update(|entries| {
+            entries
+                .remove(&self.user)
+                .map(|_| ())
+                .ok_or_else(|| AppError::Credentials("No stored credentials".to_string()))
+        })
+    }
+}
+
+fn read(path: &Path) -> Result<StoredCredentials, AppError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        tracing::error!("Error reading credential store: {e}");
+        invalid_store()
+    })?;
+
+    let stored: StoredCredentials = serde_json::from_str(&content).map_err(|e| {
+        tracing::error!("Error parsing credential store: {e}");
+        invalid_store()
+    })?;
+
+    if stored.version != STORE_VERSION {
+        tracing::error!("Unsupported credential store version {}", stored.version);
+        return Err(invalid_store());
+    }
+
+    Ok(stored)
+}
+
+/// Writes a file only readable by the current user (on Unix)
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    // permissions only apply to new files - a leftover temporary file is replaced
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, AppError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| {
+            tracing::error!("Error deriving credential store key: {e}");
+            AppError::Credentials(e.to_string())
+        })?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Encrypts a value - `aad` (the user) is authenticated, a value copied to another user fails to decrypt
+fn encrypt(cipher: &ChaCha20Poly1305, plain: &str, aad: &str) -> Result<EncryptedValue, AppError> {
+    let nonce: [u8; NONCE_LENGTH] = rand::random();
+    let payload = Payload {
+        msg: plain.as_bytes(),
+        aad: aad.as_bytes(),
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| AppError::Credentials("Error storing credentials".to_string()))?;
+
+    Ok(EncryptedValue {
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn decrypt(
+    cipher: &ChaCha20Poly1305,
+    value: &EncryptedValue,
+    aad: &str,
+) -> Result<String, AppError> {
+    let nonce = STANDARD.decode(&value.nonce).map_err(|_| invalid_store())?;
+    let ciphertext = STANDARD
+        .decode(&value.ciphertext)
+        .map_err(|_| invalid_store())?;
+
+    if nonce.len() != NONCE_LENGTH {
+        return Err(invalid_store());
+    }
+
+    let payload = Payload {
+        msg: ciphertext.as_ref(),
+        aad: aad.as_bytes(),
+    };
+    let plain = cipher
+        .decrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| invalid_store())?;
+
+    String::from_utf8(plain).map_err(|_| invalid_store())
+}
+
+fn invalid_store() -> AppError {
+    AppError::Credentials("Invalid credential store".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn unlock(dir: &TempDir) -> Arc<FileCredentialStore> {
+        Arc::new(FileCredentialStore::unlock(dir.path(), PASSPHRASE).unwrap())
+    }
+
+    /// Changes the stored file, e.g. to tamper with it
+    fn modify(dir: &TempDir, f: impl FnOnce(&mut serde_json::Value)) {
+        let path = dir.path().join(CREDENTIALS_FILE);
+        let mut stored: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        f(&mut stored);
+        std::fs::write(&path, stored.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = unlock(&dir);
+
+        store.entry("profile-a").set_dracoon_env("token-a").unwrap();
+        store.entry("profile-b").set_dracoon_env("token-b").unwrap();
+        assert!(FileCredentialStore::exists(dir.path()));
+
+        // reopened with the same passphrase
+        let store = unlock(&dir);
+        assert_eq!(
+            store.entry("profile-a").get_dracoon_env().unwrap(),
+            "token-a"
+        );
+        assert_eq!(
+            store.entry("profile-b").get_dracoon_env().unwrap(),
+            "token-b"
+        );
+
+        store.entry("profile-a").delete_dracoon_env().unwrap();
+        assert!(store.entry("profile-a").get_dracoon_env().is_err());
+        assert_eq!(
+            store.entry("profile-b").get_dracoon_env().unwrap(),
+            "token-b"
+        );
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        unlock(&dir);
+
+        let result = FileCredentialStore::unlock(dir.path(), "wrong passphrase");
+
+        assert!(matches!(result, Err(AppError::InvalidPassphrase)));
+    }
+
+    #[test]
+    fn test_tampered_ciphertext() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = unlock(&dir);
+        store.entry("profile").set_dracoon_env("token").unwrap();
+
+        modify(&dir, |stored| {
+            let value = &mut stored["entries"]["profile"]["ciphertext"];
+            let mut ciphertext = STANDARD.decode(value.as_str().unwrap()).unwrap();
+            ciphertext[0] ^= 1;
+            *value = STANDARD.encode(ciphertext).into();
+        });
+
+        assert_eq!(
+            store.entry("profile").get_dracoon_env(),
+            Err(invalid_store())
+        );
+    }
+
+    #[test]
+    fn test_value_is_bound_to_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = unlock(&dir);
+        store.entry("victim").set_dracoon_env("token").unwrap();
+
+        modify(&dir, |stored| {
+            let value = stored["entries"]["victim"].clone();
+            stored["entries"]["attacker"] = value;
+        });
+
+        assert_eq!(
+            store.entry("attacker").get_dracoon_env(),
+            Err(invalid_store())
+        );
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        unlock(&dir);
+
+        modify(&dir, |stored| {
+            stored["version"] = (STORE_VERSION + 1).into()
+        });
+
+        assert_eq!(
+            FileCredentialStore::unlock(dir.path(), PASSPHRASE).err(),
+            Some(invalid_store())
+        );
+    }
+
+    #[test]
+    fn test_delete_missing_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = unlock(&dir);
+        store.entry("profile").set_dracoon_env("token").unwrap();
+
+        assert!(store.entry("missing").delete_dracoon_env().is_err());
+        assert_eq!(store.entry("profile").get_dracoon_env().unwrap(), "token");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = unlock(&dir);
+        store.entry("profile").set_dracoon_env("token").unwrap();
+
+        let metadata = std::fs::metadata(dir.path().join(CREDENTIALS_FILE)).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+}
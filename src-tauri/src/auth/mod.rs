@@ -1,85 +1,339 @@
 use std::time::Instant;
 
-use credentials::HandleCredentials;
-use dco3::{Dracoon, Public};
-use keyring::Entry;
-use tauri::State;
+use callback::CallbackListener;
+use credentials::SerializedCredentialStore;
+pub use credentials::{CredentialBackend, CredentialManager, Credentials};
+use file_credentials::FileCredentialStore;
+pub use pkce::{exchange_auth_code, PkceChallenge};
+use dco3::{auth::Disconnected, Dracoon, Public};
+use tauri::{AppHandle, Manager, State};
 
+mod callback;
 mod credentials;
+mod file_credentials;
+mod pkce;
 use crate::{
-    config::{log_dracoon_error, APPLICATION_NAME},
-    models::{AppAuth, AppState, SerializedUserAccount},
+    config::{
+        get_client_credentials, get_or_create_config_dir, OAuthClientConfig, APPLICATION_NAME,
+    },
+    error::AppError,
+    models::{AppAuth, AppState, Profile, SerializedUserAccount},
+    network::{with_timeout, NetworkSettings},
+    settings::{apply_settings, save_settings, AppSettings},
+    profiles::save_profiles,
 };
 
 const SERVICE_NAME: &str = "dcadmin";
+const CLIENT_SECRET_SUFFIX: &str = "#oauth-client-secret";
+pub const AUTH_CONNECTED_EVENT: &str = "auth-connected";
+pub const AUTH_FAILED_EVENT: &str = "auth-failed";
 
 #[tauri::command]
-pub async fn init_auth_code_flow(url: String, state: State<'_, AppState>) -> Result<bool, String> {
-    let now = Instant::now();
-    let (dcadmin_client_id, dcadmin_client_secret) = crate::config::get_client_credentials();
+pub async fn init_auth_code_flow(
+    url: String,
+    profile: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let timer = state.timer("init_auth_code_flow");
 
     let url_without_https = url.trim_start_matches("https://");
+    let profile_name = profile.unwrap_or_else(|| url_without_https.to_string());
 
-    let entry = Entry::new(SERVICE_NAME, &url_without_https).map_err(|e| {
-        tracing::error!("Error creating keyring entry: {e}");
-        e.to_string()
-    })?;
+    let profile = state.get_or_create_profile(&profile_name, &url).await?;
+    state.set_active_profile(&profile_name).await?;
+    save_profiles(&state).await?;
 
-    let client = Dracoon::builder()
-        .with_base_url(url)
-        .with_client_id(dcadmin_client_id)
-        .with_client_secret(dcadmin_client_secret)
-        .with_user_agent(APPLICATION_NAME)
-        .build()
-        .map_err(|e| {
-            tracing::error!("Error building client: {e}");
-            e.to_string()
-        })?;
+    let entry = profile_credentials(&state, &profile).await?;
+
+    if profile.custom_oauth_client().await.is_some() {
+        load_client_secret(&state, &profile).await?;
+    }
+
+    let oauth_client = profile.oauth_client().await?;
 
     if let Ok(refresh_token) = entry.get_dracoon_env() {
+        let client = build_client(&url, &oauth_client, oauth_client.redirect_uri.clone())?;
+
         tracing::info!("Found refresh token in keyring");
-        state.init_client(client).await;
-        state.set_refresh_token(refresh_token).await;
-        state.set_entry(entry).await;
-        let elapsed = now.elapsed().as_millis();
+        profile.init_client(client).await;
+        profile.set_refresh_token(refresh_token).await;
+        profile.set_entry(entry).await;
+        let elapsed = timer.elapsed().as_millis();
         tracing::info!("Initialized refresh token flow in {elapsed} ms");
+        timer.finish();
         return Ok(true);
     }
 
     tracing::info!("No refresh token found in keyring");
 
-    let authorize_url = client.get_authorize_url();
+    // fall back to manual auth code input if no loopback port is available
+    let listener = match &oauth_client.redirect_uri {
+        Some(redirect_uri) => CallbackListener::bind_to(redirect_uri).await,
+        None => CallbackListener::bind().await,
+    }
+    .map_err(|e| tracing::warn!("Falling back to manual auth code input: {e}"))
+    .ok();
 
-    open::that(authorize_url.clone()).map_err(|e| e.to_string())?;
+    let redirect_uri = match (&listener, &oauth_client.redirect_uri) {
+        (Some(listener), _) => listener.redirect_uri(),
+        (None, Some(redirect_uri)) => redirect_uri.clone(),
+        (None, None) => format!("{}/oauth/callback", url.trim_end_matches('/')),
+    };
 
-    state.init_client(client).await;
-    state.set_auth_code().await;
-    state.set_entry(entry).await;
+    let client = build_client(&url, &oauth_client, Some(redirect_uri.clone()))?;
 
-    let elapsed = now.elapsed().as_millis();
+    let pkce = PkceChallenge::new();
+    let oauth_state = callback::generate_state();
+    let authorize_url = format!(
+        "{}&state={oauth_state}&{}",
+        client.get_authorize_url(),
+        pkce.authorize_params()
+    );
+
+    profile.init_client(client).await;
+    profile.set_auth_code(pkce, redirect_uri).await;
+    profile.set_entry(entry).await;
+
+    if let Some(listener) = listener {
+        let network = state.network_settings().await;
+        let task_profile = profile.clone();
+        let task = tauri::async_runtime::spawn(async move {
+            let profile = task_profile;
+            let result = match listener.wait_for_code(&oauth_state).await {
+                Ok(auth_code) => {
+                    tracing::info!("Received auth code via loopback redirect");
+                    connect_with_auth(Some(auth_code), &profile, &network).await
+                }
+                Err(e) => Err(e),
+            };
+
+            let emitted = match result {
+                Ok(user_account) => app.emit_all(AUTH_CONNECTED_EVENT, user_account),
+                Err(e) => app.emit_all(AUTH_FAILED_EVENT, e),
+            };
+
+            if let Err(e) = emitted {
+                tracing::error!("Error emitting auth event: {e}");
+            }
+        });
+
+        profile.set_callback_task(task);
+    }
+
+    open::that(authorize_url).map_err(|e| {
+        tracing::error!("Error opening browser: {e}");
+        AppError::from(e)
+    })?;
+
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Initialized auth code flow in {elapsed} ms");
+    timer.finish();
     Ok(false)
 }
 
+pub fn build_client(
+    url: &str,
+    oauth_client: &OAuthClientConfig,
+    redirect_uri: Option<String>,
+) -> Result<Dracoon<Disconnected>, AppError> {
+    let mut builder = Dracoon::builder()
+        .with_base_url(url)
+        .with_client_id(&oauth_client.client_id)
+        .with_client_secret(&oauth_client.client_secret)
+        .with_user_agent(APPLICATION_NAME);
+
+    if let Some(redirect_uri) = redirect_uri {
+        builder = builder.with_redirect_uri(redirect_uri);
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::from(e).logged("Error building client"))
+}
+
+/// Credentials of a profile - the default profile (named after the url) keeps the entry of previous versions
+async fn profile_credentials(state: &AppState, profile: &Profile) -> Result<Credentials, AppError> {
+    state
+        .credentials()
+        .entry(SERVICE_NAME, &credentials_user(profile))
+        .await
+}
+
+/// Secret of the custom OAuth client of a profile - stored next to its refresh token
+async fn client_secret_credentials(
+    state: &AppState,
+    profile: &Profile,
+) -> Result<Credentials, AppError> {
+    let user = format!("{}{CLIENT_SECRET_SUFFIX}", credentials_user(profile));
+    state.credentials().entry(SERVICE_NAME, &user).await
+}
+
+fn credentials_user(profile: &Profile) -> String {
+    let url_without_https = profile.url().trim_start_matches("https://");
+
+    if profile.name() == url_without_https {
+        url_without_https.to_string()
+    } else {
+        format!("{url_without_https}#{}", profile.name())
+    }
+}
+
+/// Loads the secret of the custom OAuth client of a profile from the credential store
+pub async fn load_client_secret(state: &AppState, profile: &Profile) -> Result<(), AppError> {
+    if profile.custom_oauth_client().await.is_none() {
+        return Err(AppError::InvalidInput(
+            "No custom OAuth client configured".to_string(),
+        ));
+    }
+
+    if profile.has_client_secret().await {
+        return Ok(());
+    }
+
+    let client_secret = client_secret_credentials(state, profile)
+        .await?
+        .get_dracoon_env()
+        .map_err(|e| {
+            tracing::error!("No OAuth client secret stored for profile {}", profile.name());
+            e
+        })?;
+
+    profile.set_client_secret(client_secret).await;
+    Ok(())
+}
+
+/// Stores the secret of the custom OAuth client of a profile - `None` deletes it
+pub async fn store_client_secret(
+    state: &AppState,
+    profile: &Profile,
+    client_secret: Option<&str>,
+) -> Result<(), AppError> {
+    let entry = client_secret_credentials(state, profile).await?;
+
+    match client_secret {
+        Some(client_secret) => entry.set_dracoon_env(client_secret),
+        None => entry.delete_dracoon_env(),
+    }
+}
+
+pub async fn delete_refresh_token(state: &AppState, profile: &Profile) -> Result<(), AppError> {
+    match profile.take_entry().await {
+        Some(entry) => entry.delete_dracoon_env(),
+        None => profile_credentials(state, profile).await?.delete_dracoon_env(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_credential_store(
+    state: State<'_, AppState>,
+) -> Result<SerializedCredentialStore, AppError> {
+    let credentials = state.credentials();
+
+    Ok(SerializedCredentialStore {
+        backend: credentials.backend().await,
+        resolved_backend: credentials.resolved_backend().await,
+        keyring_available: credentials.keyring_available(),
+        is_unlocked: credentials.is_unlocked().await,
+        file_store_exists: FileCredentialStore::exists(&get_or_create_config_dir()),
+    })
+}
+
+#[tauri::command]
+pub async fn set_credential_backend(
+    backend: CredentialBackend,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let config_dir = get_or_create_config_dir();
+    let previous = state.settings().await;
+    let settings = AppSettings {
+        credential_backend: backend,
+        ..previous.clone()
+    };
+
+    save_settings(&config_dir, &settings)?;
+    apply_settings(&config_dir, &previous, &settings, &state).await;
+
+    tracing::info!("Set credential backend to {backend:?}");
+    Ok(())
+}
+
+/// Unlocks the encrypted credential store - sets the passphrase if the store does not exist yet
+#[tauri::command]
+pub async fn unlock_credential_store(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let timer = state.timer("unlock_credential_store");
+
+    state
+        .credentials()
+        .unlock(&get_or_create_config_dir(), &passphrase)
+        .await?;
+
+    let elapsed = timer.elapsed().as_millis();
+    tracing::info!("Unlocked credential store in {elapsed} ms");
+    timer.finish();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn logout(state: State<'_, AppState>) -> Result<(), AppError> {
+    let timer = state.timer("logout");
+    let profile = state.active_profile().await?;
+
+    // local session is cleared even if the server is not reachable
+    let revoked = profile.logout().await;
+
+    if let Err(e) = delete_refresh_token(&state, &profile).await {
+        tracing::warn!("Error deleting refresh token from keyring: {e}");
+    }
+
+    let elapsed = timer.elapsed().as_millis();
+    tracing::info!("Logged out profile {} in {elapsed} ms", profile.name());
+    if revoked.is_ok() {
+        timer.finish();
+    }
+
+    revoked
+}
+
 #[tauri::command]
 pub async fn connect(
     auth_code: Option<String>,
     state: State<'_, AppState>,
-) -> Result<SerializedUserAccount, String> {
+) -> Result<SerializedUserAccount, AppError> {
+    let timer = state.timer("connect");
+    let profile = state.active_profile().await?;
+    let network = state.network_settings().await;
+    let user_account = connect_with_auth(auth_code, &profile, &network).await?;
+
+    // the redirect is not needed anymore - the listener would report a failure once it times out
+    profile.abort_callback_task();
+
+    timer.finish();
+    Ok(user_account)
+}
+
+async fn connect_with_auth(
+    auth_code: Option<String>,
+    profile: &Profile,
+    network: &NetworkSettings,
+) -> Result<SerializedUserAccount, AppError> {
     let now = Instant::now();
-    let user_account = match &*state.app_auth().read().await {
+    let user_account = match &*profile.app_auth().read().await {
         AppAuth::RefreshToken(refresh_token) => {
-            match state.connect_refresh_token(refresh_token).await {
+            match profile.connect_refresh_token(refresh_token).await {
                 Ok((user_account, new_refresh_token)) => {
                     tracing::info!("Connected with refresh token");
-                    let entry = state.entry().read().await;
+                    let entry = profile.entry().read().await;
 
                     if let Some(entry) = &*entry {
                         entry
                             .set_dracoon_env(&new_refresh_token)
                             .map_err(|e| {
                                 tracing::error!("Error updating refresh token in keyring: {e}");
-                                e.to_string()})?;
+                                e})?;
                     }
 
                     tracing::info!("Updated refresh token in keyring");
@@ -87,23 +341,25 @@ pub async fn connect(
                 }
                 Err(e) => {
                     tracing::error!("Error connecting with refresh token: {e}");
-                    let entry = state.entry().read().await;
+                    let entry = profile.entry().read().await;
                     if let Some(entry) = &*entry {
                         entry.delete_dracoon_env().map_err(|e| {
                             tracing::error!("Error deleting invalid refresh token from keyring: {e}");
-                            e.to_string()})?;
+                            e})?;
                         tracing::info!("Deleted invalid refresh token from keyring: {e}");
                     }
 
-                    return Err(e.to_string());
+                    return Err(e);
                 }
             }
         }
-        AppAuth::AuthCode => {
+        AppAuth::AuthCode { pkce, redirect_uri } => {
             if let Some(auth_code) = auth_code {
                 tracing::info!("Connecting with auth code");
-                let (user_account, refresh_token) = state.connect(auth_code).await?;
-                let entry = state.entry().read().await;
+                let (user_account, refresh_token) = profile
+                    .connect(auth_code, pkce, redirect_uri, network)
+                    .await?;
+                let entry = profile.entry().read().await;
 
                 tracing::info!("Connected with auth code");
 
@@ -112,24 +368,27 @@ pub async fn connect(
                         .set_dracoon_env(&refresh_token)
                         .map_err(|e| {
                             tracing::error!("Error storing refresh token in keyring: {e}");
-                            e.to_string()})?;
+                            e})?;
                 }
 
                 tracing::info!("Stored refresh token in keyring");
                 user_account
             } else {
                 tracing::error!("No auth code provided");
-                return Err("No auth code provided".to_string());
+                return Err(AppError::InvalidInput("No auth code provided".to_string()));
             }
         }
-        AppAuth::Unset => return Err("No auth code or refresh token provided".to_string()),
+        AppAuth::Unset => return Err(AppError::NotConnected),
     };
 
-    let client = state.get_client().await?;
-    let version_data = client.public().get_software_version().await.map_err(|e| {
-        log_dracoon_error(&e, Some("Error fetching software version"));
-        e.to_string()
-    })?;
+    profile.set_roles(&user_account).await;
+
+    let client = profile.get_client().await?;
+    let version_data = client
+        .public()
+        .get_software_version()
+        .await
+        .map_err(|e| AppError::from(e).logged("Error fetching software version"))?;
 
     let elapsed = now.elapsed().as_millis();
     tracing::info!("Connected in {elapsed} ms");
@@ -137,33 +396,38 @@ pub async fn connect(
 }
 
 #[tauri::command]
-pub async fn validate_dracoon_url(url: String) -> Result<bool, String> {
-    let now = Instant::now();
-    let (dcadmin_client_id, dcadmin_client_secret) = crate::config::get_client_credentials();
+pub async fn validate_dracoon_url(
+    url: String,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let timer = state.timer("validate_dracoon_url");
 
-    let client = Dracoon::builder()
-        .with_base_url(&url)
-        .with_client_id(dcadmin_client_id)
-        .with_client_secret(dcadmin_client_secret)
-        .with_user_agent(APPLICATION_NAME)
-        .build()
-        .map_err(|e| {
-            tracing::error!("Error building public client: {e}");
-            e.to_string()
-        })?;
+    // unknown profiles are validated with the bundled OAuth client
+    let oauth_client = match &profile {
+        Some(name) => match state.get_profile(name).await {
+            Ok(profile) => {
+                if profile.custom_oauth_client().await.is_some() {
+                    load_client_secret(&state, &profile).await?;
+                }
+                profile.oauth_client().await?
+            }
+            Err(_) => get_client_credentials()?,
+        },
+        None => get_client_credentials()?,
+    };
 
-    let result = client
-        .public()
-        .get_software_version()
+    let client = build_client(&url, &oauth_client, None)?;
+    let timeout = state.network_settings().await.timeout();
+
+    let result = with_timeout(timeout, client.public().get_software_version())
         .await
         .map(|_| true)
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error validating Dracoon url"));
-            e.to_string()
-        });
+        .map_err(|e| e.logged("Error validating Dracoon url"));
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Validated Dracoon url in {elapsed} ms");
+    timer.finish();
 
     if result.is_err() {
         tracing::error!("Error validating Dracoon url - invalid url: {url}");
@@ -0,0 +1,160 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{AppError, HttpErrorDetails},
+    network::{http_client, NetworkSettings},
+};
+
+const CODE_VERIFIER_LENGTH: usize = 64;
+const CODE_CHALLENGE_METHOD: &str = "S256";
+const TOKEN_PATH: &str = "oauth/token";
+
+/// PKCE code verifier and S256 challenge (RFC 7636) for a single authorization request
+#[derive(Clone)]
+pub struct PkceChallenge {
+    verifier: String,
+    challenge: String,
+}
+
+impl PkceChallenge {
+    pub fn new() -> Self {
+        let verifier: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(CODE_VERIFIER_LENGTH)
+            .map(char::from)
+            .collect();
+
+        Self::from_verifier(verifier)
+    }
+
+    fn from_verifier(verifier: String) -> Self {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+
+    pub fn verifier(&self) -> &str {
+        &self.verifier
+    }
+
+    pub fn authorize_params(&self) -> String {
+        format!(
+            "code_challenge={}&code_challenge_method={CODE_CHALLENGE_METHOD}",
+            self.challenge
+        )
+    }
+}
+
+impl Default for PkceChallenge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    refresh_token: String,
+}
+
+/// Exchanges the auth code including the code verifier and returns the refresh token.
+/// Transient failures are retried with the retry policy - the only request honouring `Retry-After`.
+pub async fn exchange_auth_code(
+    network: &NetworkSettings,
+    base_url: &str,
+    client_id: &str,
+    client_secret: &str,
+    auth_code: &str,
+    redirect_uri: &str,
+    pkce: &PkceChallenge,
+) -> Result<String, AppError> {
+    let token_url = format!("{}/{TOKEN_PATH}", base_url.trim_end_matches('/'));
+
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("code", auth_code),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", pkce.verifier()),
+    ];
+
+    let http_client = http_client(network)?;
+    let response = network
+        .retry_policy()
+        .run("Error exchanging auth code", || async {
+            let response = http_client
+                .post(&token_url)
+                .basic_auth(client_id, Some(client_secret))
+                .form(&params)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let details =
+                    HttpErrorDetails::from_response(response, "Error exchanging auth code").await;
+                return Err(AppError::from(details));
+            }
+
+            Ok(response)
+        })
+        .await
+        .map_err(|e| e.logged("Error exchanging auth code"))?;
+
+    let token = response.json::<TokenResponse>().await.map_err(|e| {
+        tracing::error!("Error parsing token response: {e}");
+        AppError::Internal(e.to_string())
+    })?;
+
+    Ok(token.refresh_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verifier_is_valid() {
+        let pkce = PkceChallenge::new();
+
+        // RFC 7636 4.1: 43 - 128 characters from the unreserved set
+        assert_eq!(pkce.verifier().len(), CODE_VERIFIER_LENGTH);
+        assert!(pkce.verifier().chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_verifier_is_random() {
+        assert_ne!(
+            PkceChallenge::new().verifier(),
+            PkceChallenge::new().verifier()
+        );
+    }
+
+    #[test]
+    fn test_s256_challenge() {
+        // RFC 7636 appendix B
+        let pkce =
+            PkceChallenge::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+
+        assert_eq!(
+            pkce.challenge,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_authorize_params() {
+        let pkce = PkceChallenge::from_verifier("verifier".to_string());
+
+        assert_eq!(
+            pkce.authorize_params(),
+            format!(
+                "code_challenge={}&code_challenge_method=S256",
+                pkce.challenge
+            )
+        );
+    }
+}
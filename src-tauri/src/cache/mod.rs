@@ -0,0 +1,49 @@
+use tauri::State;
+
+use crate::{error::AppError, AppState};
+use models::{InvalidateCacheParams, SerializedProfileCacheStats};
+
+pub use models::{CacheKey, CacheName, CachePolicy, TrackedCache};
+
+mod models;
+
+#[tauri::command]
+pub async fn get_cache_stats(
+    state: State<'_, AppState>,
+) -> Result<Vec<SerializedProfileCacheStats>, AppError> {
+    let mut stats = Vec::new();
+
+    for profile in state.profiles().await {
+        stats.push(SerializedProfileCacheStats {
+            profile: profile.name().to_string(),
+            url: profile.url().to_string(),
+            caches: profile.cache_stats().await,
+        });
+    }
+
+    Ok(stats)
+}
+
+/// Drops cached entries - optionally only of one cache and / or one DRACOON instance
+#[tauri::command]
+pub async fn invalidate_cache(
+    params: Option<InvalidateCacheParams>,
+    state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let params = params.unwrap_or_default();
+    let mut invalidated = 0;
+
+    for profile in state.profiles().await {
+        invalidated += profile
+            .invalidate_cache(params.cache, params.url.as_deref())
+            .await;
+    }
+
+    tracing::info!(
+        "Invalidated {invalidated} cache entries (cache: {}, url: {})",
+        params.cache.map(|cache| cache.as_str()).unwrap_or("all"),
+        params.url.as_deref().unwrap_or("all")
+    );
+
+    Ok(invalidated)
+}
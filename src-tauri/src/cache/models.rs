@@ -0,0 +1,216 @@
+use std::{
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::setup_cache, models::SerializedCacheStats};
+
+/// Keys of all caches start with the DRACOON url - used to invalidate by instance
+pub trait CacheKey {
+    fn url(&self) -> &str;
+}
+
+impl CacheKey for String {
+    fn url(&self) -> &str {
+        self
+    }
+}
+
+/// How a page request uses the page caches
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CachePolicy {
+    /// reuse a cached page, cache a fetched page
+    Use,
+    /// skip the cached page (force refresh), cache the fetched page
+    Refresh,
+    /// reuse a cached page, do not cache a fetched page - bulk fetches would evict the pages cached while browsing
+    ReadOnly,
+}
+
+impl CachePolicy {
+    pub fn from_force_refresh(force_refresh: Option<bool>) -> Self {
+        if force_refresh.unwrap_or_default() {
+            CachePolicy::Refresh
+        } else {
+            CachePolicy::Use
+        }
+    }
+
+    pub fn reads(&self) -> bool {
+        *self != CachePolicy::Refresh
+    }
+
+    pub fn writes(&self) -> bool {
+        *self != CachePolicy::ReadOnly
+    }
+}
+
+#[derive(Clone)]
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: DateTime<Utc>,
+}
+
+/// moka cache that counts hits and misses and keeps the insertion time of each entry
+#[derive(Clone)]
+pub struct TrackedCache<K, V> {
+    name: &'static str,
+    cache: Cache<K, CacheEntry<V>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl<K, V> TrackedCache<K, V>
+where
+    K: CacheKey + Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new(name: &'static str, max_capacity: u64, ttl: Duration) -> Self {
+        TrackedCache {
+            name,
+            cache: setup_cache(max_capacity, ttl),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub async fn get(&self, key: &K) -> Option<V> {
+        match self.cache.get(key).await {
+            Some(entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub async fn insert(&self, key: K, value: V) {
+        let entry = CacheEntry {
+            value,
+            inserted_at: Utc::now(),
+        };
+        self.cache.insert(key, entry).await;
+    }
+
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+    }
+
+    /// Drops all entries or only those of a DRACOON instance - returns the number of dropped entries
+    pub async fn invalidate(&self, url: Option<&str>) -> u64 {
+        match url {
+            Some(url) => self.invalidate_url(url).await,
+            None => {
+                self.cache.run_pending_tasks().await;
+                let entries = self.cache.entry_count();
+                self.cache.invalidate_all();
+                entries
+            }
+        }
+    }
+
+    async fn invalidate_url(&self, url: &str) -> u64 {
+        let keys = self
+            .cache
+            .iter()
+            .filter(|(key, _)| same_url(key.url(), url))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        for key in &keys {
+            self.cache.invalidate(key.as_ref()).await;
+        }
+
+        keys.len() as u64
+    }
+
+    pub async fn stats(&self) -> SerializedCacheStats {
+        // entry count is only accurate after pending evictions ran
+        self.cache.run_pending_tasks().await;
+
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let oldest = self.cache.iter().map(|(_, entry)| entry.inserted_at).min();
+
+        SerializedCacheStats {
+            name: self.name.to_string(),
+            entries: self.cache.entry_count(),
+            hits,
+            misses,
+            hit_rate: if hits + misses > 0 {
+                hits as f64 / (hits + misses) as f64
+            } else {
+                0.0
+            },
+            oldest_entry_age_secs: oldest
+                .map(|inserted_at| (Utc::now() - inserted_at).num_seconds().max(0) as u64),
+        }
+    }
+}
+
+/// Compares urls regardless of scheme and trailing slash
+fn same_url(key_url: &str, url: &str) -> bool {
+    let normalize = |url: &str| {
+        url.trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_end_matches('/')
+            .to_lowercase()
+    };
+
+    normalize(key_url) == normalize(url)
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheName {
+    Permissions,
+    Customer,
+    Events,
+    Operations,
+    Users,
+    Groups,
+    Group,
+    GroupUsers,
+}
+
+impl CacheName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheName::Permissions => "permissions",
+            CacheName::Customer => "customer",
+            CacheName::Events => "events",
+            CacheName::Operations => "operations",
+            CacheName::Users => "users",
+            CacheName::Groups => "groups",
+            CacheName::Group => "group",
+            CacheName::GroupUsers => "groupUsers",
+        }
+    }
+}
+
+/// Without cache and url, all caches of all profiles are cleared
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidateCacheParams {
+    pub cache: Option<CacheName>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedProfileCacheStats {
+    pub profile: String,
+    pub url: String,
+    pub caches: Vec<SerializedCacheStats>,
+}
@@ -2,90 +2,187 @@ use std::{
     fs::OpenOptions,
     hash::Hash,
     path::{Path, PathBuf},
-    time::{Duration, SystemTime},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 
-use dco3::DracoonClientError;
 use moka::future::Cache;
+use serde::{Deserialize, Serialize};
 use tracing::level_filters::LevelFilter;
-use tracing_subscriber::EnvFilter;
-pub const DEFAULT_CACHE_TTL: u64 = 60 * 5; // 5 minutes
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use crate::{
+    error::AppError,
+    logs::{rotate_logs, RedactingWriter, Redactor},
+    settings::{LogFormat, LogLevel, LogSettings, RedactionSettings},
+};
+
 pub const APPLICATION_NAME: &str = "dcadmin";
-pub const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024; // 10MB
+pub const LOG_FILE: &str = "dcadmin.log";
 pub const DEFAULT_NO_DEBUG_MESSAGE: &str = "No details";
 
-pub fn get_client_credentials() -> (String, String) {
-    let client_id = include_str!("../.env")
-        .split('\n')
-        .next()
-        .expect("env file has more than one line")
-        .split("DCADMIN_CLIENT_ID=")
-        .nth(1)
-        .expect("CLIENT_ID MUST be provided");
-    let client_secret = include_str!("../.env")
-        .split('\n')
-        .nth(1)
-        .expect("env file has more than one line")
-        .split("DCADMIN_CLIENT_SECRET=")
-        .nth(1)
-        .expect("CLIENT_SECRET MUST be provided");
-
-    (client_id.to_string(), client_secret.to_string())
-}
+const BUNDLED_ENV: &str = include_str!("../.env");
 
-pub fn setup_logging(config_dir: &Path, debug: bool) {
-    let log_file_path = config_dir.join("dcadmin.log");
+/// OAuth client used to authenticate against a DRACOON instance
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClientConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: Option<String>,
+}
 
-    // get file size
-    let size = if let Ok(metadata) = std::fs::metadata(&log_file_path) {
-        metadata.len()
-    } else {
-        0
+/// Returns the OAuth client bundled at compile time (default for all profiles)
+pub fn get_client_credentials() -> Result<OAuthClientConfig, AppError> {
+    let env_value = |key: &str| {
+        BUNDLED_ENV
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
     };
 
-    // set up env filter
-    let env_filter = if debug {
+    match (
+        env_value("DCADMIN_CLIENT_ID"),
+        env_value("DCADMIN_CLIENT_SECRET"),
+    ) {
+        (Some(client_id), Some(client_secret)) => Ok(OAuthClientConfig {
+            client_id,
+            client_secret,
+            redirect_uri: None,
+        }),
+        _ => {
+            tracing::error!("No bundled OAuth client available");
+            Err(AppError::Internal("No OAuth client configured".to_string()))
+        }
+    }
+}
+
+/// Handles to change the log level and redaction at runtime
+pub struct LogHandle {
+    filter: reload::Handle<EnvFilter, Registry>,
+    redactor: Arc<RwLock<Redactor>>,
+}
+
+impl LogHandle {
+    pub fn set_level(&self, level: LogLevel) {
+        match self.filter.reload(log_filter(level)) {
+            Ok(()) => tracing::info!("Set log level to {level:?}"),
+            Err(e) => tracing::error!("Error setting log level: {e}"),
+        }
+    }
+
+    pub fn set_redaction(&self, settings: &RedactionSettings) {
+        let redactor = match Redactor::new(settings) {
+            Ok(redactor) => redactor,
+            Err(e) => {
+                tracing::error!("Error applying redaction rules: {e}");
+                return;
+            }
+        };
+
+        match self.redactor.write() {
+            Ok(mut current) => {
+                *current = redactor;
+                tracing::info!("Updated log redaction rules");
+            }
+            Err(e) => tracing::error!("Error applying redaction rules: {e}"),
+        }
+    }
+}
+
+/// Env filter for the given level - directives from `RUST_LOG` are kept
+pub fn log_filter(level: LogLevel) -> EnvFilter {
+    let level: LevelFilter = level.into();
+    if level >= LevelFilter::DEBUG {
         EnvFilter::from_default_env()
-            .add_directive(LevelFilter::DEBUG.into())
+            .add_directive(level.into())
             .add_directive("hyper_utils=warn".parse().expect("invalid crate setup"))
     } else {
-        EnvFilter::from_default_env().add_directive(LevelFilter::INFO.into())
+        EnvFilter::from_default_env().add_directive(level.into())
+    }
+}
+
+pub fn setup_logging(config_dir: &Path, settings: &LogSettings) -> LogHandle {
+    // rotate before the log file is opened
+    let mut warnings = rotate_logs(config_dir, settings);
+
+    // set up env filter - reloadable to change the level at runtime
+    let (env_filter, filter_handle) = reload::Layer::new(log_filter(settings.level));
+
+    // invalid custom rules are skipped - the built-in rules always apply
+    let redactor = Redactor::new(&settings.redaction).unwrap_or_else(|e| {
+        warnings.push(format!("{e} - using built-in redaction rules"));
+        Redactor::new(&RedactionSettings {
+            patterns: Vec::new(),
+            ..settings.redaction.clone()
+        })
+        .expect("invalid built-in redaction rules")
+    });
+    let redactor = Arc::new(RwLock::new(redactor));
+
+    // logs go to stderr if the log file cannot be opened - both are redacted
+    let (writer, open_error) = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config_dir.join(LOG_FILE))
+    {
+        Ok(log_file) => (
+            BoxMakeWriter::new(RedactingWriter::new(Mutex::new(log_file), redactor.clone())),
+            None,
+        ),
+        Err(e) => (
+            BoxMakeWriter::new(RedactingWriter::new(std::io::stderr, redactor.clone())),
+            Some(e),
+        ),
     };
 
     // set up log format
-    let log_format = tracing_subscriber::fmt::format()
-        .with_level(true)
-        .with_thread_names(false)
-        .with_target(true)
-        .with_ansi(false)
-        .compact();
-
-    // rotate log file if bigger than 10MB
-    let log_file = if size > MAX_LOG_SIZE {
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or(Duration::from_secs(0))
-            .as_secs();
-        let rotated_log_file_path = config_dir.join(format!("dcadmin.log.{}", timestamp));
-        std::fs::rename(&log_file_path, &rotated_log_file_path).expect("Failed to rotate log file");
-        OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_file_path)
-            .expect("Failed to open log file")
-    } else {
-        OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_file_path)
-            .expect("Failed to open log file")
+    let fmt_layer = match settings.format {
+        LogFormat::Text => {
+            let log_format = tracing_subscriber::fmt::format()
+                .with_level(true)
+                .with_thread_names(false)
+                .with_target(true)
+                .with_ansi(false)
+                .compact();
+
+            fmt::layer()
+                .event_format(log_format)
+                .with_writer(writer)
+                .boxed()
+        }
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_thread_names(false)
+            .with_target(true)
+            .with_writer(writer)
+            .boxed(),
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .event_format(log_format)
-        .with_writer(std::sync::Mutex::new(log_file))
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(fmt_layer)
         .init();
+
+    if let Some(e) = open_error {
+        tracing::error!("Error opening log file - logging to stderr: {e}");
+    }
+
+    for warning in warnings {
+        tracing::warn!("{warning}");
+    }
+
+    LogHandle {
+        filter: filter_handle,
+        redactor,
+    }
 }
 
 pub fn get_or_create_config_dir() -> PathBuf {
@@ -103,27 +200,10 @@ pub fn get_or_create_config_dir() -> PathBuf {
 
 pub fn setup_cache<K: Hash + Eq + Send + Sync + 'static, V: Clone + Send + Sync + 'static>(
     max_capacity: u64,
-    ttl: Option<Duration>,
+    ttl: Duration,
 ) -> Cache<K, V> {
     Cache::builder()
-        .time_to_live(ttl.unwrap_or(Duration::from_secs(DEFAULT_CACHE_TTL)))
+        .time_to_live(ttl)
         .max_capacity(max_capacity)
         .build()
 }
-
-pub fn log_dracoon_error(e: &DracoonClientError, msg: Option<&str>) {
-    if let Some(msg) = msg {
-        tracing::error!("{msg}: {e}");
-    } else {
-        tracing::error!("Error: {e}");
-    }
-
-    if let Some(e) = e.get_http_error() {
-        tracing::error!(
-            "HTTP error: {} - {} ({})",
-            e.code(),
-            e.error_message(),
-            e.debug_info().unwrap_or(DEFAULT_NO_DEBUG_MESSAGE.into())
-        );
-    }
-}
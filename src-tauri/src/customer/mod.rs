@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::AppState;
+use crate::{error::AppError, AppState};
 use dco3::User;
 pub use models::SerializedCustomerInfo;
 use tauri::State;
@@ -9,29 +9,37 @@ mod models;
 
 #[tauri::command]
 pub async fn get_customer_info(
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedCustomerInfo, String> {
+) -> Result<SerializedCustomerInfo, AppError> {
     let client = state.get_client().await?;
 
-    if let Some(info) = state
-        .get_customer_cache()
-        .get(&client.get_base_url().to_string())
-        .await
-    {
+    let cached = if force_refresh.unwrap_or_default() {
+        None
+    } else {
+        state
+            .get_customer_cache()
+            .await?
+            .get(&client.get_base_url().to_string())
+            .await
+    };
+
+    if let Some(info) = cached {
         return Ok((*info).clone());
     }
 
-    let info = client
-        .user()
-        .get_customer_info()
-        .await
-        .map_err(|e| e.to_string())?;
+    let info = state
+        .call("Error fetching customer info", |client| async move {
+            client.user().get_customer_info().await
+        })
+        .await?;
 
     let serializable_info: SerializedCustomerInfo = info.into();
     let serializable_info = Arc::new(serializable_info);
 
     state
         .get_customer_cache()
+        .await?
         .insert(client.get_base_url().to_string(), serializable_info.clone())
         .await;
 
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use dco3::Public;
+use serde::Serialize;
+use tauri::State;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    config::get_or_create_config_dir,
+    error::AppError,
+    logs::{log_files, read_log_content, redact, COMPRESSED_SUFFIX},
+    models::{CommandTiming, SerializedCacheStats},
+    network::with_timeout,
+    settings::AppSettings,
+    AppState,
+};
+
+use models::{SerializedSoftwareVersion, SerializedSystemInfo};
+
+mod models;
+
+// current log file and the latest rotated ones
+const MAX_DIAGNOSTICS_LOG_FILES: usize = 3;
+
+struct Diagnostics {
+    system: SerializedSystemInfo,
+    settings: AppSettings,
+    caches: Vec<SerializedCacheStats>,
+    timings: Vec<CommandTiming>,
+    log_files: Vec<PathBuf>,
+}
+
+async fn system_info(state: &AppState) -> SerializedSystemInfo {
+    // a single attempt without session recovery - diagnostics never change the session state
+    let timeout = state.network_settings().await.timeout();
+    let version = match state.get_client().await {
+        Ok(client) => with_timeout(timeout, client.public().get_software_version()).await,
+        Err(e) => Err(e),
+    };
+
+    let (server, server_error) = match version {
+        Ok(version) => (Some(SerializedSoftwareVersion::from(version)), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    SerializedSystemInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        profile: state.active_profile_name().await,
+        server,
+        server_error,
+    }
+}
+
+fn write_json<T: Serialize>(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    value: &T,
+    options: FileOptions,
+) -> Result<(), AppError> {
+    let content = serde_json::to_string_pretty(value)?;
+    zip.start_file(name, options)?;
+    zip.write_all(redact(&content).as_bytes())?;
+    Ok(())
+}
+
+fn write_bundle(path: &Path, diagnostics: &Diagnostics) -> Result<(), AppError> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    write_json(&mut zip, "system.json", &diagnostics.system, options)?;
+    write_json(&mut zip, "settings.json", &diagnostics.settings, options)?;
+    write_json(&mut zip, "caches.json", &diagnostics.caches, options)?;
+    write_json(&mut zip, "timings.json", &diagnostics.timings, options)?;
+
+    for log_file in &diagnostics.log_files {
+        // rotated files are added uncompressed
+        let name = log_file
+            .file_name()
+            .map(|name| {
+                name.to_string_lossy()
+                    .trim_end_matches(COMPRESSED_SUFFIX)
+                    .to_string()
+            })
+            .unwrap_or_default();
+
+        let content = read_log_content(log_file)?;
+        zip.start_file(format!("logs/{name}"), options)?;
+        zip.write_all(redact(&content).as_bytes())?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Writes a zip with system info, settings, cache statistics, command timings and recent logs.
+/// Secrets and personal data are redacted.
+#[tauri::command]
+pub async fn export_diagnostics(path: String, state: State<'_, AppState>) -> Result<(), AppError> {
+    let timer = state.timer("export_diagnostics");
+
+    let caches = match state.active_profile().await {
+        Ok(profile) => profile.cache_stats().await,
+        Err(_) => Vec::new(),
+    };
+
+    let log_files = log_files(&get_or_create_config_dir())?
+        .into_iter()
+        .take(MAX_DIAGNOSTICS_LOG_FILES)
+        .collect();
+
+    let diagnostics = Diagnostics {
+        system: system_info(&state).await,
+        settings: state.settings().await,
+        caches,
+        timings: state.timings(),
+        log_files,
+    };
+
+    let path = PathBuf::from(path);
+    let result = tauri::async_runtime::spawn_blocking({
+        let path = path.clone();
+        move || write_bundle(&path, &diagnostics)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))
+    .and_then(|result| result);
+
+    if let Err(e) = result {
+        tracing::error!("Error exporting diagnostics: {e}");
+        // no partial zip is left behind
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+
+    let elapsed = timer.elapsed().as_millis();
+    tracing::info!("Exported diagnostics in {elapsed} ms");
+    timer.finish();
+    Ok(())
+}
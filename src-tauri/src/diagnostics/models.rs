@@ -0,0 +1,35 @@
+use dco3::public::SoftwareVersionData;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSystemInfo {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub created_at: String,
+    pub profile: Option<String>,
+    pub server: Option<SerializedSoftwareVersion>,
+    /// reason why the server version is missing (e.g. not connected)
+    pub server_error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSoftwareVersion {
+    pub rest_api_version: String,
+    pub sds_server_version: String,
+    pub build_date: String,
+    pub is_dracoon_cloud: Option<bool>,
+}
+
+impl From<SoftwareVersionData> for SerializedSoftwareVersion {
+    fn from(value: SoftwareVersionData) -> Self {
+        SerializedSoftwareVersion {
+            rest_api_version: value.rest_api_version,
+            sds_server_version: value.sds_server_version,
+            build_date: value.build_date,
+            is_dracoon_cloud: value.is_dracoon_cloud,
+        }
+    }
+}
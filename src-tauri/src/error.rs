@@ -0,0 +1,385 @@
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use dco3::DracoonClientError;
+use reqwest::{header::RETRY_AFTER, Response};
+use serde::{Serialize, Serializer};
+
+use crate::config::DEFAULT_NO_DEBUG_MESSAGE;
+
+/// Error returned by all commands - serialized as `{ code, status, message, debugInfo }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// missing or expired authentication (401)
+    Unauthorized(HttpErrorDetails),
+    /// missing role or permission (403)
+    Forbidden(HttpErrorDetails),
+    NotFound(HttpErrorDetails),
+    BadRequest(HttpErrorDetails),
+    /// any other HTTP error returned by DRACOON
+    Http(HttpErrorDetails),
+    /// DRACOON not reachable
+    Network(String),
+    /// no response within the configured request timeout
+    Timeout,
+    /// TLS handshake or certificate validation failed - not retried
+    Tls(String),
+    NotConnected,
+    /// connected user lacks all of the roles required by a command
+    MissingRole(Vec<String>),
+    /// invalid params or filters passed by the frontend
+    InvalidInput(String),
+    CredentialStoreLocked,
+    InvalidPassphrase,
+    /// keyring or encrypted credential store failures
+    Credentials(String),
+    Csv(String),
+    Io(String),
+    /// snapshot database failures
+    Storage(String),
+    /// job cancelled by the user
+    Cancelled,
+    Internal(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpErrorDetails {
+    pub status: u16,
+    pub message: String,
+    pub debug_info: Option<String>,
+    /// `Retry-After` header of the response - only known for requests not sent via dco3 (the token exchange)
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpErrorDetails {
+    /// Details of a failed response of our own HTTP client - the body is kept as debug info
+    pub async fn from_response(response: Response, message: &str) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+
+        HttpErrorDetails {
+            status,
+            message: message.to_string(),
+            debug_info: Some(body),
+            retry_after,
+        }
+    }
+}
+
+/// `Retry-After` in seconds or as HTTP date - a date in the past means no delay
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Connection errors caused by TLS or certificates - retrying does not help
+fn is_tls_failure(message: &str) -> bool {
+    let message = message.to_lowercase();
+
+    ["certificate", "tls", "ssl", "handshake"]
+        .iter()
+        .any(|keyword| message.contains(keyword))
+}
+
+/// Message of an error including its sources - reqwest keeps the cause (e.g. TLS) in the source chain
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+
+    message
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAppError {
+    code: &'static str,
+    status: Option<u16>,
+    message: String,
+    debug_info: Option<String>,
+}
+
+impl AppError {
+    /// Stable code the frontend can match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::Forbidden(_) => "Forbidden",
+            AppError::NotFound(_) => "NotFound",
+            AppError::BadRequest(_) => "BadRequest",
+            AppError::Http(_) => "HttpError",
+            AppError::Network(_) => "NetworkError",
+            AppError::Timeout => "Timeout",
+            AppError::Tls(_) => "TlsError",
+            AppError::NotConnected => "NotConnected",
+            AppError::MissingRole(_) => "MissingRole",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::CredentialStoreLocked => "CredentialStoreLocked",
+            AppError::InvalidPassphrase => "InvalidPassphrase",
+            AppError::Credentials(_) => "CredentialError",
+            AppError::Csv(_) => "CsvError",
+            AppError::Io(_) => "IoError",
+            AppError::Storage(_) => "StorageError",
+            AppError::Cancelled => "Cancelled",
+            AppError::Internal(_) => "InternalError",
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        self.http_details().map(|details| details.status)
+    }
+
+    /// Message to be displayed to the user
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Unauthorized(_) => "Session expired - please log in again".to_string(),
+            AppError::Forbidden(_) => "Missing permission for this action".to_string(),
+            AppError::NotFound(_) => "Requested resource not found".to_string(),
+            AppError::BadRequest(details) | AppError::Http(details) => details.message.clone(),
+            AppError::Network(_) => "DRACOON not reachable".to_string(),
+            AppError::Timeout => "DRACOON did not respond in time".to_string(),
+            AppError::Tls(_) => {
+                "Secure connection to DRACOON failed - check the server certificate".to_string()
+            }
+            AppError::NotConnected => "Not connected to DRACOON".to_string(),
+            AppError::MissingRole(roles) => format!("Missing role: {}", roles.join(" or ")),
+            AppError::InvalidInput(message) => message.clone(),
+            AppError::CredentialStoreLocked => "Credential store is locked".to_string(),
+            AppError::InvalidPassphrase => "Invalid passphrase".to_string(),
+            AppError::Credentials(_) => "Error accessing stored credentials".to_string(),
+            AppError::Csv(_) => "Error writing CSV file".to_string(),
+            AppError::Io(_) => "Error accessing file".to_string(),
+            AppError::Storage(_) => "Error accessing snapshot store".to_string(),
+            AppError::Cancelled => "Cancelled by user".to_string(),
+            AppError::Internal(message) => message.clone(),
+        }
+    }
+
+    pub fn debug_info(&self) -> Option<String> {
+        match self {
+            AppError::Unauthorized(details)
+            | AppError::Forbidden(details)
+            | AppError::NotFound(details)
+            | AppError::BadRequest(details)
+            | AppError::Http(details) => details.debug_info.clone(),
+            AppError::Network(info)
+            | AppError::Tls(info)
+            | AppError::Credentials(info)
+            | AppError::Csv(info)
+            | AppError::Io(info)
+            | AppError::Storage(info) => Some(info.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_auth_error(&self) -> bool {
+        matches!(self, AppError::Unauthorized(_))
+    }
+
+    /// Rate limits, server errors, network failures and timeouts - worth retrying (TLS failures are not)
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AppError::Http(details) => matches!(details.status, 429 | 500 | 502 | 503 | 504),
+            AppError::Network(_) | AppError::Timeout => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, AppError::Timeout)
+    }
+
+    /// Delay requested by the server via `Retry-After`
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.http_details().and_then(|details| details.retry_after)
+    }
+
+    pub fn log(&self, msg: Option<&str>) {
+        if let Some(msg) = msg {
+            tracing::error!("{msg}: {self}");
+        } else {
+            tracing::error!("Error: {self}");
+        }
+
+        if let Some(details) = self.http_details() {
+            tracing::error!(
+                "HTTP error: {} - {} ({})",
+                details.status,
+                details.message,
+                details
+                    .debug_info
+                    .as_deref()
+                    .unwrap_or(DEFAULT_NO_DEBUG_MESSAGE)
+            );
+        }
+    }
+
+    /// Logs a failed attempt of a request that is retried
+    pub fn log_retry(&self, msg: &str, attempt: u32, max_attempts: u32, delay: Duration) {
+        tracing::warn!(
+            "{msg} (attempt {attempt} of {max_attempts}) - retrying in {} ms: {self}",
+            delay.as_millis()
+        );
+
+        if let Some(details) = self.http_details() {
+            tracing::warn!(
+                "HTTP error: {} - {} ({})",
+                details.status,
+                details.message,
+                details
+                    .debug_info
+                    .as_deref()
+                    .unwrap_or(DEFAULT_NO_DEBUG_MESSAGE)
+            );
+        }
+    }
+
+    /// Logs the error and returns it - shorthand for `map_err`
+    pub fn logged(self, msg: &str) -> Self {
+        self.log(Some(msg));
+        self
+    }
+
+    fn http_details(&self) -> Option<&HttpErrorDetails> {
+        match self {
+            AppError::Unauthorized(details)
+            | AppError::Forbidden(details)
+            | AppError::NotFound(details)
+            | AppError::BadRequest(details)
+            | AppError::Http(details) => Some(details),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpErrorDetails> for AppError {
+    fn from(value: HttpErrorDetails) -> Self {
+        match value.status {
+            400 => AppError::BadRequest(value),
+            401 => AppError::Unauthorized(value),
+            403 => AppError::Forbidden(value),
+            404 => AppError::NotFound(value),
+            _ => AppError::Http(value),
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.debug_info() {
+            Some(debug_info) => write!(f, "{} ({debug_info})", self.message()),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedAppError {
+            code: self.code(),
+            status: self.status(),
+            message: self.message(),
+            debug_info: self.debug_info(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<DracoonClientError> for AppError {
+    fn from(value: DracoonClientError) -> Self {
+        if let Some(e) = value.get_http_error() {
+            // dco3 does not pass on response headers - `Retry-After` is unknown
+            return HttpErrorDetails {
+                status: e.code(),
+                message: e.error_message(),
+                debug_info: e.debug_info(),
+                retry_after: None,
+            }
+            .into();
+        }
+
+        match value {
+            DracoonClientError::Auth(e) => AppError::Unauthorized(HttpErrorDetails {
+                status: 401,
+                message: "Authentication failed".to_string(),
+                debug_info: Some(format!("{e:?}")),
+                retry_after: None,
+            }),
+            DracoonClientError::ConnectionFailed(e) if is_tls_failure(&e) => AppError::Tls(e),
+            DracoonClientError::ConnectionFailed(e) => AppError::Network(e),
+            DracoonClientError::InvalidUrl(url) => {
+                AppError::InvalidInput(format!("Invalid DRACOON url: {url}"))
+            }
+            e => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(value: reqwest::Error) -> Self {
+        let message = error_chain(&value);
+
+        if value.is_decode() {
+            AppError::Internal(message)
+        } else if is_tls_failure(&message) {
+            AppError::Tls(message)
+        } else {
+            AppError::Network(message)
+        }
+    }
+}
+
+impl From<csv::Error> for AppError {
+    fn from(value: csv::Error) -> Self {
+        AppError::Csv(value.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(value: std::io::Error) -> Self {
+        AppError::Io(value.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(value: serde_json::Error) -> Self {
+        AppError::Internal(value.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for AppError {
+    fn from(value: zip::result::ZipError) -> Self {
+        AppError::Io(value.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(value: rusqlite::Error) -> Self {
+        AppError::Storage(value.to_string())
+    }
+}
@@ -1,33 +1,54 @@
 mod models;
 use std::{sync::Arc, time::Instant};
 
-use crate::AppState;
+use crate::{
+    error::AppError,
+    guard::{require_roles, GuardedCommand},
+    jobs::{enqueue_export, ExportRequest, Job},
+    models::Profile,
+    pagination::{Page, Paginator},
+    AppState,
+};
 use dco3::{eventlog::EventlogParams, Eventlog};
-use models::{EventListParams, SerializedEvent};
-pub use models::{EventsCacheKey, SerializedEventList, SerializedOperationTypes};
+pub(crate) use models::SerializedEvent;
+pub use models::{
+    EventListParams, EventsCacheKey, SerializedEventList, SerializedOperationTypes,
+};
 use tauri::State;
 
 #[tauri::command]
 pub async fn get_events(
     params: EventListParams,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedEventList, String> {
-    let now = Instant::now();
+) -> Result<SerializedEventList, AppError> {
+    require_roles(&state, GuardedCommand::GetEvents).await?;
+    let timer = state.timer("get_events");
     let client = state.get_client().await?;
 
     let key = EventsCacheKey::new(client.get_base_url().to_string(), params.clone());
 
-    if let Some(events) = state.get_events_cache().get(&key).await {
-        let elapsed = now.elapsed().as_millis();
+    let cached = if force_refresh.unwrap_or_default() {
+        None
+    } else {
+        state.get_events_cache().await?.get(&key).await
+    };
+
+    if let Some(events) = cached {
+        let elapsed = timer.elapsed().as_millis();
         tracing::info!("Fetched cached events in {elapsed} ms");
+        timer.finish();
         return Ok((*events).clone());
     }
 
-    let events = client
-        .eventlog()
-        .get_events(params.clone().try_into()?)
-        .await
-        .map_err(|e| e.to_string())?;
+    let eventlog_params: EventlogParams = params.clone().try_into()?;
+
+    let events = state
+        .call("Error fetching events", |client| {
+            let params = eventlog_params.clone();
+            async move { client.eventlog().get_events(params).await }
+        })
+        .await?;
 
     let serialized_events: SerializedEventList = events.into();
 
@@ -35,11 +56,13 @@ pub async fn get_events(
 
     state
         .get_events_cache()
+        .await?
         .insert(key, serialized_events.clone())
         .await;
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched events in {elapsed} ms");
+    timer.finish();
     Ok((*serialized_events).clone())
 }
 
@@ -48,81 +71,142 @@ pub async fn export_events(
     params: EventListParams,
     path: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let now = Instant::now();
-    let client = state.get_client().await?;
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportEvents).await?;
+    // invalid params are rejected before the export is queued
+    let _: EventlogParams = params.clone().try_into()?;
 
-    let mut events = client
-        .eventlog()
-        .get_events(params.clone().try_into()?)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    for offset in (500..events.range.total).step_by(500) {
-        tracing::debug!("Fetching events with offset {offset}");
-        let params = EventlogParams {
-            offset: Some(offset),
-            ..params.clone().try_into()?
-        };
-
-        let new_events = client
-            .eventlog()
-            .get_events(params)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        events.items.extend(new_events.items);
-    }
+    enqueue_export(&state, ExportRequest::Events(params), path).await
+}
 
-    let elapsed_all_events = now.elapsed().as_millis();
-    tracing::info!("Fetched all events in {elapsed_all_events} ms");
+/// Writes all events matching the params page by page - returns the number of written rows
+pub(crate) async fn write_events(
+    state: &AppState,
+    params: EventListParams,
+    job: &mut Job,
+) -> Result<u64, AppError> {
+    let now = Instant::now();
+    let export = state.settings().await.export;
+    let profile = job.profile();
+    let eventlog_params: EventlogParams = params.try_into()?;
 
-    let serializable_events: Vec<SerializedEvent> = events
-        .items
-        .into_iter()
-        .map(|e| e.into())
-        .collect::<Vec<_>>();
+    let mut writer = job.writer(export.format)?;
+    let mut offset = job.resume_offset();
+    let paginator = Paginator::from(&export).starting_at(offset);
 
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    for_each_events_page(state, &profile, eventlog_params, paginator, |page| {
+        offset += page.items.len() as u64;
+        writer.write_items(page.items)?;
+        job.page_written(&mut writer, offset, page.total)
+    })
+    .await?;
 
-    for event in serializable_events {
-        csv_writer.serialize(event).map_err(|e| e.to_string())?;
-    }
+    let event_count = writer.finish()?;
 
-    csv_writer.flush().map_err(|e| e.to_string())?;
+    let elapsed_export = now.elapsed().as_millis();
+    tracing::info!("Exported {event_count} events in {elapsed_export} ms");
 
-    Ok(())
+    Ok(event_count)
 }
 
 #[tauri::command]
 pub async fn get_operation_types(
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedOperationTypes, String> {
-    let noe = Instant::now();
+) -> Result<SerializedOperationTypes, AppError> {
+    require_roles(&state, GuardedCommand::GetOperationTypes).await?;
+    let timer = state.timer("get_operation_types");
     let client = state.get_client().await?;
 
     let key = client.get_base_url().to_string();
 
-    if let Some(operation_types) = state.get_operations_cache().get(&key).await {
-        let elapsed = noe.elapsed().as_millis();
+    let cached = if force_refresh.unwrap_or_default() {
+        None
+    } else {
+        state.get_operations_cache().await?.get(&key).await
+    };
+
+    if let Some(operation_types) = cached {
+        let elapsed = timer.elapsed().as_millis();
         tracing::info!("Fetched cached operation types in {elapsed} ms");
+        timer.finish();
         return Ok((*operation_types).clone());
     }
 
-    let operation_types: SerializedOperationTypes = client
-        .eventlog()
-        .get_event_operations()
-        .await
-        .map_err(|e| e.to_string())?.into();
+    let operation_types: SerializedOperationTypes = state
+        .call("Error fetching operation types", |client| async move {
+            client.eventlog().get_event_operations().await
+        })
+        .await?
+        .into();
 
     let operation_types = Arc::new(operation_types);
 
     state
         .get_operations_cache()
+        .await?
         .insert(key, operation_types.clone())
         .await;
 
-    let elapsed = noe.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched operation types in {elapsed} ms");
+    timer.finish();
     Ok((*operation_types).clone())
 }
+
+/// Fetches all pages of events matching the params and passes them on in order
+pub(crate) async fn for_each_events_page<C>(
+    state: &AppState,
+    profile: &Profile,
+    params: EventlogParams,
+    paginator: Paginator,
+    on_page: C,
+) -> Result<u64, AppError>
+where
+    C: FnMut(Page<SerializedEvent>) -> Result<(), AppError>,
+{
+    paginator
+        .for_each_page(
+            |offset, limit| {
+                let params = EventlogParams {
+                    offset: Some(offset),
+                    limit: Some(limit),
+                    ..params.clone()
+                };
+
+                async move {
+                    let events = state
+                        .call_with(profile, "Error fetching events", |client| {
+                            let params = params.clone();
+                            async move { client.eventlog().get_events(params).await }
+                        })
+                        .await?;
+
+                    Ok(Page::new(
+                        events.range.total,
+                        events.items.into_iter().map(|event| event.into()).collect(),
+                    ))
+                }
+            },
+            on_page,
+        )
+        .await
+}
+
+/// Fetches all pages of events matching the params
+pub(crate) async fn get_all_events(
+    state: &AppState,
+    profile: &Profile,
+    params: EventlogParams,
+    paginator: Paginator,
+) -> Result<Vec<SerializedEvent>, AppError> {
+    let mut events = Vec::new();
+
+    for_each_events_page(state, profile, params, paginator, |page| {
+        events.extend(page.items);
+        Ok(())
+    })
+    .await?;
+
+    Ok(events)
+}
@@ -5,7 +5,7 @@ use dco3::eventlog::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::models::Range;
+use crate::{cache::CacheKey, error::AppError, models::Range};
 
 #[derive(PartialEq, Eq)]
 pub struct EventsCacheKey {
@@ -28,6 +28,12 @@ impl From<&EventsCacheKey> for String {
     }
 }
 
+impl CacheKey for EventsCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
 impl Hash for EventsCacheKey {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let key: String = self.into();
@@ -87,8 +93,8 @@ impl EventListParams {
 
 
 impl TryFrom<EventListParams> for EventlogParams {
-    type Error = String;
-    fn try_from(value: EventListParams) -> Result<EventlogParams, String> {
+    type Error = AppError;
+    fn try_from(value: EventListParams) -> Result<EventlogParams, AppError> {
         let date_start = value
             .from_date
             .as_ref()
@@ -97,7 +103,7 @@ impl TryFrom<EventListParams> for EventlogParams {
                     .map(|dt| dt.and_utc())
                     .map_err(|e| {
             
-                        e.to_string()
+                        AppError::InvalidInput(e.to_string())
                     })
             })
             .transpose()?;
@@ -108,7 +114,7 @@ impl TryFrom<EventListParams> for EventlogParams {
             .map(|s| {
                 chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S.%fZ")
                     .map(|dt| dt.and_utc())
-                    .map_err(|e| e.to_string())
+                    .map_err(|e| AppError::InvalidInput(e.to_string()))
             })
             .transpose()?;
 
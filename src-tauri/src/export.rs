@@ -0,0 +1,212 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{error::AppError, settings::ExportFormat};
+
+enum ExportSink {
+    Csv(csv::Writer<File>),
+    /// JSON array written item by item - opened with the first item
+    Json {
+        writer: BufWriter<File>,
+        empty: bool,
+    },
+}
+
+/// Writes exported items page by page in the configured format.
+/// The file is removed if the writer is dropped before `finish` succeeded - unless it was flushed
+/// for a checkpoint, then it is kept to resume the export.
+pub struct ExportWriter {
+    path: PathBuf,
+    sink: Option<ExportSink>,
+    items: u64,
+    resumable: bool,
+}
+
+impl ExportWriter {
+    pub fn create(path: &str, format: ExportFormat) -> Result<Self, AppError> {
+        let sink = match format {
+            ExportFormat::Csv => {
+                let csv_writer = csv::Writer::from_path(path).map_err(|e| {
+                    tracing::error!("Error creating CSV writer: {e}");
+                    AppError::from(e)
+                })?;
+                ExportSink::Csv(csv_writer)
+            }
+            ExportFormat::Json => {
+                let file = File::create(path).map_err(|e| {
+                    tracing::error!("Error creating export file: {e}");
+                    AppError::from(e)
+                })?;
+                ExportSink::Json {
+                    writer: BufWriter::new(file),
+                    empty: true,
+                }
+            }
+        };
+
+        Ok(ExportWriter {
+            path: PathBuf::from(path),
+            sink: Some(sink),
+            items: 0,
+            resumable: false,
+        })
+    }
+
+    /// Reopens an incomplete export to append items - content written after the checkpoint is dropped
+    pub fn append(
+        path: &str,
+        format: ExportFormat,
+        items: u64,
+        bytes: u64,
+    ) -> Result<Self, AppError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|mut file| {
+                file.set_len(bytes)?;
+                file.seek(SeekFrom::End(0))?;
+                Ok(file)
+            })
+            .map_err(|e| {
+                tracing::error!("Error reopening export file: {e}");
+                AppError::from(e)
+            })?;
+
+        let sink = match format {
+            // the header was written with the first item
+            ExportFormat::Csv => ExportSink::Csv(
+                csv::WriterBuilder::new()
+                    .has_headers(items == 0)
+                    .from_writer(file),
+            ),
+            ExportFormat::Json => ExportSink::Json {
+                writer: BufWriter::new(file),
+                empty: items == 0,
+            },
+        };
+
+        Ok(ExportWriter {
+            path: PathBuf::from(path),
+            sink: Some(sink),
+            items,
+            resumable: true,
+        })
+    }
+
+    pub fn write_items<T: Serialize>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<(), AppError> {
+        let sink = self
+            .sink
+            .as_mut()
+            .ok_or_else(|| AppError::Internal("Export already finished".to_string()))?;
+
+        for item in items {
+            match sink {
+                ExportSink::Csv(csv_writer) => csv_writer.serialize(item).map_err(|e| {
+                    tracing::error!("Error serializing item: {e}");
+                    AppError::from(e)
+                })?,
+                ExportSink::Json { writer, empty } => {
+                    let separator: &[u8] = if *empty { b"[\n  " } else { b",\n  " };
+                    writer.write_all(separator)?;
+                    serde_json::to_writer(&mut *writer, &item).map_err(|e| {
+                        tracing::error!("Error serializing item: {e}");
+                        AppError::Io(e.to_string())
+                    })?;
+                    *empty = false;
+                }
+            }
+            self.items += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Number of items written so far
+    pub fn written(&self) -> u64 {
+        self.items
+    }
+
+    /// Writes buffered items to the file for a checkpoint - returns the file size
+    pub fn flush(&mut self) -> Result<u64, AppError> {
+        let file = match self.sink.as_mut() {
+            Some(ExportSink::Csv(csv_writer)) => {
+                csv_writer.flush()?;
+                csv_writer.get_ref()
+            }
+            Some(ExportSink::Json { writer, .. }) => {
+                writer.flush()?;
+                writer.get_ref()
+            }
+            None => return Err(AppError::Internal("Export already finished".to_string())),
+        };
+
+        let bytes = file.metadata()?.len();
+        self.resumable = true;
+        Ok(bytes)
+    }
+
+    /// Flushes the file - returns the number of written items
+    pub fn finish(mut self) -> Result<u64, AppError> {
+        let result = match self.sink.take() {
+            Some(ExportSink::Csv(mut csv_writer)) => csv_writer.flush().map_err(|e| {
+                tracing::error!("Error flushing CSV writer: {e}");
+                AppError::from(e)
+            }),
+            Some(ExportSink::Json { mut writer, empty }) => {
+                let closing: &[u8] = if empty { b"[]\n" } else { b"\n]\n" };
+                writer
+                    .write_all(closing)
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| {
+                        tracing::error!("Error flushing export file: {e}");
+                        AppError::from(e)
+                    })
+            }
+            None => Ok(()),
+        };
+
+        match result {
+            Ok(()) => Ok(self.items),
+            Err(e) => {
+                self.discard();
+                Err(e)
+            }
+        }
+    }
+
+    fn discard(&self) {
+        if self.resumable {
+            tracing::warn!(
+                "Keeping incomplete export {} to resume",
+                self.path.display()
+            );
+        } else {
+            self.remove_partial_file();
+        }
+    }
+
+    fn remove_partial_file(&self) {
+        tracing::warn!("Removing incomplete export {}", self.path.display());
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::error!("Error removing incomplete export: {e}");
+        }
+    }
+}
+
+impl Drop for ExportWriter {
+    fn drop(&mut self) {
+        // sink is only taken by finish - an export failed or was aborted
+        if let Some(sink) = self.sink.take() {
+            drop(sink);
+            self.discard();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::AppError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterOperator {
+    Eq,
+    Neq,
+    Cn,
+    Ge,
+    Le,
+}
+
+/// Single DRACOON filter expression (`field:op:value`)
+#[derive(Debug, PartialEq, Eq)]
+pub struct FilterExpression {
+    pub field: String,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+
+/// Parses a DRACOON filter string (`field:op:value|field:op:value`) - all expressions must match.
+/// Values may contain `:` (e.g. timestamps), empty expressions are skipped.
+pub fn parse_filter(filter: &str) -> Result<Vec<FilterExpression>, AppError> {
+    filter
+        .split('|')
+        .filter(|expression| !expression.is_empty())
+        .map(parse_expression)
+        .collect()
+}
+
+fn parse_expression(expression: &str) -> Result<FilterExpression, AppError> {
+    let mut parts = expression.splitn(3, ':');
+
+    let (field, operator, value) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(field), Some(operator), Some(value)) if !field.is_empty() => (field, operator, value),
+        _ => {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid filter: {expression}"
+            )))
+        }
+    };
+
+    let operator = match operator {
+        "eq" => FilterOperator::Eq,
+        "neq" => FilterOperator::Neq,
+        "cn" => FilterOperator::Cn,
+        "ge" => FilterOperator::Ge,
+        "le" => FilterOperator::Le,
+        _ => {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid filter operator: {operator}"
+            )))
+        }
+    };
+
+    Ok(FilterExpression {
+        field: field.to_string(),
+        operator,
+        value: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(field: &str, operator: FilterOperator, value: &str) -> FilterExpression {
+        FilterExpression {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_single_expression() {
+        assert_eq!(
+            parse_filter("userName:cn:Doe").unwrap(),
+            vec![expression("userName", FilterOperator::Cn, "Doe")]
+        );
+    }
+
+    #[test]
+    fn test_parse_all_operators() {
+        let expressions = parse_filter("a:eq:1|b:neq:2|c:cn:3|d:ge:4|e:le:5").unwrap();
+        let operators: Vec<_> = expressions.iter().map(|e| e.operator).collect();
+
+        assert_eq!(
+            operators,
+            vec![
+                FilterOperator::Eq,
+                FilterOperator::Neq,
+                FilterOperator::Cn,
+                FilterOperator::Ge,
+                FilterOperator::Le
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_keeps_colons() {
+        assert_eq!(
+            parse_filter("expireAt:ge:2024-01-01T10:00:00Z").unwrap(),
+            vec![expression(
+                "expireAt",
+                FilterOperator::Ge,
+                "2024-01-01T10:00:00Z"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_skips_empty_expressions() {
+        assert_eq!(
+            parse_filter("isLocked:eq:false|").unwrap(),
+            vec![expression("isLocked", FilterOperator::Eq, "false")]
+        );
+        assert!(parse_filter("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_value() {
+        assert_eq!(
+            parse_filter("firstName:eq:").unwrap(),
+            vec![expression("firstName", FilterOperator::Eq, "")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!(parse_filter("userName").is_err());
+        assert!(parse_filter("userName:cn").is_err());
+        assert!(parse_filter(":cn:doe").is_err());
+        assert!(parse_filter("userName:like:doe").is_err());
+        assert!(parse_filter("userName:cn:doe|broken").is_err());
+    }
+}
@@ -1,13 +1,20 @@
-use std::time::Instant;
-
-use crate::{config::log_dracoon_error, models::ListParams, AppState};
-use dco3::{
-    auth::Connected,
-    groups::{GroupList, GroupUserList},
-    Dracoon, Groups,
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    cache::CachePolicy,
+    error::AppError,
+    guard::{require_roles, GuardedCommand},
+    jobs::{enqueue_export, ExportRequest, Job},
+    models::{ListParams, Profile},
+    pagination::{Page, Paginator},
+    AppState,
 };
-use models::{
-    FlattenedSerializedGroup, SerializedGroupInfo, SerializedGroupList, SerializedGroupUserList, SerializedGroupUserWithGroupInfo
+use dco3::{Groups, ListAllParams};
+pub(crate) use models::{SerializedGroup, SerializedGroupUser};
+use models::{FlattenedSerializedGroup, SerializedGroupUserWithGroupInfo};
+pub use models::{
+    GroupCacheKey, GroupUsersCacheKey, GroupsCacheKey, SerializedGroupInfo, SerializedGroupList,
+    SerializedGroupUserList,
 };
 use tauri::State;
 mod models;
@@ -15,50 +22,68 @@ mod models;
 #[tauri::command]
 pub async fn get_group(
     group_id: u64,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedGroupInfo, String> {
-    let now = Instant::now();
+) -> Result<SerializedGroupInfo, AppError> {
+    require_roles(&state, GuardedCommand::GetGroup).await?;
+    let timer = state.timer("get_group");
     let client = state.get_client().await?;
 
-    let group = client
-        .groups()
-        .get_group(group_id)
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching group"));
-            e.to_string()
-        })?;
+    let key = GroupCacheKey::new(client.get_base_url().to_string(), group_id);
+
+    let cached = if force_refresh.unwrap_or_default() {
+        None
+    } else {
+        state.get_group_cache().await?.get(&key).await
+    };
+
+    if let Some(group) = cached {
+        let elapsed = timer.elapsed().as_millis();
+        tracing::info!("Fetched cached group {group_id} in {elapsed} ms");
+        timer.finish();
+        return Ok((*group).clone());
+    }
 
-    let elapsed = now.elapsed().as_millis();
+    let group = state
+        .call("Error fetching group", |client| async move {
+            client.groups().get_group(group_id).await
+        })
+        .await?;
+
+    let group: Arc<SerializedGroupInfo> = Arc::new(group.into());
+
+    state
+        .get_group_cache()
+        .await?
+        .insert(key, group.clone())
+        .await;
+
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched group {group_id} in {elapsed} ms");
+    timer.finish();
 
-    Ok(group.into())
+    Ok((*group).clone())
 }
 
-
 #[tauri::command]
 pub async fn get_groups(
     params: ListParams,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedGroupList, String> {
-    let now = Instant::now();
-    let client = state.get_client().await?;
-    let params = params.try_into()?;
+) -> Result<SerializedGroupList, AppError> {
+    require_roles(&state, GuardedCommand::GetGroups).await?;
+    let timer = state.timer("get_groups");
 
-    let groups = client
-        .groups()
-        .get_groups(Some(params))
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching groups"));
-            e.to_string()
-        })?;
+    let profile = state.active_profile().await?;
+    let cache = CachePolicy::from_force_refresh(force_refresh);
+    let groups = get_groups_page(&state, &profile, params, cache).await?;
 
     let group_count = groups.items.len();
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched {group_count} groups in {elapsed} ms");
-    Ok(groups.into())
+    timer.finish();
+    Ok((*groups).clone())
 }
 
 #[tauri::command]
@@ -66,103 +91,32 @@ pub async fn export_groups(
     params: ListParams,
     path: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let now = Instant::now();
-
-    let client = state.get_client().await?;
-
-    let mut groups = client
-        .groups()
-        .get_groups(Some(params.clone().try_into()?))
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching groups"));
-            e.to_string()
-        })?;
-
-    let elapsed_first_500_groups = now.elapsed().as_millis();
-    let group_count = groups.items.len();
-
-    tracing::info!("Fetched first {group_count} groups in {elapsed_first_500_groups} ms");
-
-    for offset in (500..groups.range.total).step_by(500) {
-        tracing::debug!("Fetching groups with offset {offset}");
-        let params = ListParams {
-            offset: Some(offset),
-            ..params.clone()
-        };
-
-        let new_groups = client
-            .groups()
-            .get_groups(Some(params.try_into()?))
-            .await
-            .map_err(|e| {
-                log_dracoon_error(&e, Some("Error fetching groups"));
-                e.to_string()
-            })?;
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportGroups).await?;
 
-        groups.items.extend(new_groups.items);
-    }
-
-    let elapsed = now.elapsed().as_millis();
-    tracing::info!("Fetched all groups in {elapsed} ms");
-
-    let serialized_groups: SerializedGroupList = groups.into();
-    let flattened_groups: Vec<FlattenedSerializedGroup> = serialized_groups
-        .items
-        .into_iter()
-        .map(|group| group.into())
-        .collect();
-
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {e}");
-        e.to_string()
-    })?;
-
-    for group in flattened_groups {
-        csv_writer.serialize(group).map_err(|e| {
-            tracing::error!("Error serializing group: {e}");
-            e.to_string()
-        })?;
-    }
-
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {e}");
-        e.to_string()
-    })?;
-
-    let elapsed_csv = now.elapsed().as_millis();
-    tracing::info!("Exported all groups to CSV in {elapsed_csv} ms");
-
-    Ok(())
+    enqueue_export(&state, ExportRequest::Groups(params), path).await
 }
 
 #[tauri::command]
 pub async fn get_group_users(
     group_id: u64,
     params: ListParams,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedGroupUserList, String> {
-    let now = Instant::now();
-    let client = state.get_client().await?;
-    let params = params.try_into()?;
-
-    dbg!(&params);
+) -> Result<SerializedGroupUserList, AppError> {
+    require_roles(&state, GuardedCommand::GetGroupUsers).await?;
+    let timer = state.timer("get_group_users");
 
-    let group_users = client
-        .groups()
-        .get_group_users(group_id, Some(params))
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching group users"));
-            e.to_string()
-        })?;
+    let profile = state.active_profile().await?;
+    let cache = CachePolicy::from_force_refresh(force_refresh);
+    let group_users = get_group_users_page(&state, &profile, group_id, params, cache).await?;
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     let user_count = group_users.items.len();
     tracing::info!("Fetched {user_count} group users in {elapsed} ms");
+    timer.finish();
 
-    Ok(group_users.into())
+    Ok((*group_users).clone())
 }
 
 #[tauri::command]
@@ -171,151 +125,341 @@ pub async fn export_group_users(
     group_id: u64,
     params: ListParams,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportGroupUsers).await?;
+
+    enqueue_export(&state, ExportRequest::GroupUsers { group_id, params }, path).await
+}
+
+#[tauri::command]
+pub async fn export_all_group_users(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportAllGroupUsers).await?;
+
+    enqueue_export(&state, ExportRequest::AllGroupUsers, path).await
+}
+
+/// Writes all groups matching the params page by page - returns the number of written rows
+pub(crate) async fn write_groups(
+    state: &AppState,
+    params: ListParams,
+    job: &mut Job,
+) -> Result<u64, AppError> {
     let now = Instant::now();
+    let export = state.settings().await.export;
+    let profile = job.profile();
 
-    let client = state.get_client().await?;
+    let mut writer = job.writer(export.format)?;
+    let mut offset = job.resume_offset();
+    let paginator = Paginator::from(&export).starting_at(offset);
+
+    for_each_groups_page(state, &profile, params, paginator, |page| {
+        offset += page.items.len() as u64;
+        writer.write_items(page.items.into_iter().map(FlattenedSerializedGroup::from))?;
+        job.page_written(&mut writer, offset, page.total)
+    })
+    .await?;
 
-    let group_users = get_all_group_users(&client, group_id, params.clone()).await?;
+    let group_count = writer.finish()?;
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed_export = now.elapsed().as_millis();
+    tracing::info!("Exported {group_count} groups in {elapsed_export} ms");
 
-    tracing::info!("Fetched all group users in {elapsed} ms");
+    Ok(group_count)
+}
 
-    let serialized_group_users: SerializedGroupUserList = group_users.into();
+/// Writes the users of a group page by page - returns the number of written rows
+pub(crate) async fn write_group_users(
+    state: &AppState,
+    group_id: u64,
+    params: ListParams,
+    job: &mut Job,
+) -> Result<u64, AppError> {
+    let now = Instant::now();
+    let export = state.settings().await.export;
+    let profile = job.profile();
 
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {e}");
-        e.to_string()
-    })?;
+    let mut writer = job.writer(export.format)?;
+    let mut offset = job.resume_offset();
+    let paginator = Paginator::from(&export).starting_at(offset);
 
-    for group_user in serialized_group_users.items {
-        csv_writer.serialize(group_user).map_err(|e| {
-            tracing::error!("Error serializing group user: {e}");
-            e.to_string()
-        })?;
-    }
+    for_each_group_users_page(state, &profile, group_id, params, paginator, |page| {
+        offset += page.items.len() as u64;
+        writer.write_items(page.items)?;
+        job.page_written(&mut writer, offset, page.total)
+    })
+    .await?;
 
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {e}");
-        e.to_string()
-    })?;
+    let user_count = writer.finish()?;
 
-    let elapsed_csv = now.elapsed().as_millis();
-    tracing::info!("Exported all group users for group {group_id} to CSV in {elapsed_csv} ms");
+    let elapsed_export = now.elapsed().as_millis();
+    tracing::info!("Exported {user_count} users of group {group_id} in {elapsed_export} ms");
 
-    Ok(())
+    Ok(user_count)
 }
 
-#[tauri::command]
-pub async fn export_all_group_users(
-    path: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
+/// Writes the users of all groups group by group - returns the number of written rows
+pub(crate) async fn write_all_group_users(
+    state: &AppState,
+    job: &mut Job,
+) -> Result<u64, AppError> {
     let now = Instant::now();
-    let client = state.get_client().await?;
+    let export = state.settings().await.export;
+    let paginator: Paginator = (&export).into();
+    let profile = job.profile();
+
+    let mut groups = Vec::new();
+
+    for_each_groups_page(state, &profile, ListParams::default(), paginator, |page| {
+        groups.extend(page.items);
+        job.page_fetched(0)
+    })
+    .await?;
 
-    let groups = get_all_groups(&client).await?;
     let elapsed_groups = now.elapsed().as_millis();
-    let group_count = groups.items.len();
+    let group_count = groups.len();
     tracing::info!("Fetched {group_count} groups in {elapsed_groups} ms");
 
-    let mut all_group_users = Vec::new();
+    // groups are processed by id - a resumed export continues after the last written group
+    groups.sort_by_key(|group| group.id);
+    if let Some(last_id) = job.resume_last_id() {
+        groups.retain(|group| group.id > last_id);
+    }
+    job.set_total(groups.len() as u64);
+
+    let mut writer = job.writer(export.format)?;
+
+    for group in &groups {
+        for_each_group_users_page(
+            state,
+            &profile,
+            group.id,
+            ListParams::default(),
+            paginator,
+            |page| {
+                writer.write_items(
+                    page.items
+                        .into_iter()
+                        .map(|user| SerializedGroupUserWithGroupInfo::from((group, user))),
+                )?;
+                job.page_fetched(writer.written())
+            },
+        )
+        .await?;
+
+        job.item_written(&mut writer, group.id)?;
+    }
 
-    for group in groups {
-        let group_users = get_all_group_users(&client, group.id, ListParams::default()).await?;
-        let serialized_group_users: Vec<SerializedGroupUserWithGroupInfo> = group_users
-            .into_iter()
-            .map(|user| (group.clone(), user).into())
-            .collect();
+    let user_count = writer.finish()?;
 
-        all_group_users.extend(serialized_group_users);
-    }
+    let elapsed_export = now.elapsed().as_millis();
+    tracing::info!("Exported {user_count} group users in {elapsed_export} ms");
 
-    let mut csv_writer = csv::Writer::from_path(path.clone()).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {e}");
-        e.to_string()
-    })?;
+    Ok(user_count)
+}
 
-    for group_user in all_group_users {
-        csv_writer.serialize(group_user).map_err(|e| {
-            tracing::error!("Error serializing group user: {e}");
-            e.to_string()
-        })?;
+/// Fetches a page of groups - pages are cached per url and params
+async fn get_groups_page(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    cache: CachePolicy,
+) -> Result<Arc<SerializedGroupList>, AppError> {
+    let client = profile.get_client().await?;
+    let key = GroupsCacheKey::new(client.get_base_url().to_string(), params.clone());
+
+    let cached = if cache.reads() {
+        profile.get_groups_cache().await.get(&key).await
+    } else {
+        None
+    };
+
+    if let Some(groups) = cached {
+        tracing::debug!("Using cached groups (offset {:?})", params.offset);
+        return Ok(groups);
     }
 
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {e}");
-        e.to_string()
-    })?;
+    let params: ListAllParams = params.try_into()?;
 
-    Ok(())
-}
+    let groups = state
+        .call_with(profile, "Error fetching groups", |client| {
+            let params = params.clone();
+            async move { client.groups().get_groups(Some(params)).await }
+        })
+        .await?;
 
-async fn get_all_groups(client: &Dracoon<Connected>) -> Result<GroupList, String> {
-    let mut groups = client.groups().get_groups(None).await.map_err(|e| {
-        log_dracoon_error(&e, Some("Error fetching groups"));
-        e.to_string()
-    })?;
-
-    if groups.range.total > 500 {
-        for offset in (500..groups.range.total).step_by(500) {
-            tracing::debug!("Fetching groups with offset {offset}");
-            let params = ListParams {
-                offset: Some(offset),
-                ..Default::default()
-            };
-
-            let new_groups = client
-                .groups()
-                .get_groups(Some(params.try_into()?))
-                .await
-                .map_err(|e| {
-                    log_dracoon_error(&e, Some("Error fetching groups"));
-                    e.to_string()
-                })?;
-
-            groups.items.extend(new_groups.items);
-        }
+    let groups: Arc<SerializedGroupList> = Arc::new(groups.into());
+
+    if cache.writes() {
+        profile
+            .get_groups_cache()
+            .await
+            .insert(key, groups.clone())
+            .await;
     }
 
     Ok(groups)
 }
 
-async fn get_all_group_users(
-    client: &Dracoon<Connected>,
+/// Fetches a page of users of a group - pages are cached per url, group and params
+async fn get_group_users_page(
+    state: &AppState,
+    profile: &Profile,
     group_id: u64,
     params: ListParams,
-) -> Result<GroupUserList, String> {
-    let mut group_users = client
-        .groups()
-        .get_group_users(group_id, Some(params.clone().try_into()?))
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching group users"));
-            e.to_string()
-        })?;
-
-    if group_users.range.total > 500 {
-        for offset in (500..group_users.range.total).step_by(500) {
-            tracing::debug!("Fetching group users with offset {offset}");
-            let params = ListParams {
-                offset: Some(offset),
-                ..params.clone()
-            };
-
-            let new_group_users = client
-                .groups()
-                .get_group_users(group_id, Some(params.try_into()?))
-                .await
-                .map_err(|e| {
-                    log_dracoon_error(&e, Some("Error fetching group users"));
-                    e.to_string()
-                })?;
-
-            group_users.items.extend(new_group_users.items);
-        }
+    cache: CachePolicy,
+) -> Result<Arc<SerializedGroupUserList>, AppError> {
+    let client = profile.get_client().await?;
+    let key = GroupUsersCacheKey::new(
+        client.get_base_url().to_string(),
+        group_id,
+        params.clone(),
+    );
+
+    let cached = if cache.reads() {
+        profile.get_group_users_cache().await.get(&key).await
+    } else {
+        None
+    };
+
+    if let Some(group_users) = cached {
+        tracing::debug!(
+            "Using cached users of group {group_id} (offset {:?})",
+            params.offset
+        );
+        return Ok(group_users);
+    }
+
+    let params: ListAllParams = params.try_into()?;
+
+    let group_users = state
+        .call_with(profile, "Error fetching group users", |client| {
+            let params = params.clone();
+            async move { client.groups().get_group_users(group_id, Some(params)).await }
+        })
+        .await?;
+
+    let group_users: Arc<SerializedGroupUserList> = Arc::new(group_users.into());
+
+    if cache.writes() {
+        profile
+            .get_group_users_cache()
+            .await
+            .insert(key, group_users.clone())
+            .await;
     }
 
     Ok(group_users)
 }
+
+/// Fetches all pages of groups matching the params and passes them on in order.
+/// Cached pages are reused, fetched pages are not cached - a bulk fetch would evict the pages cached while browsing.
+pub(crate) async fn for_each_groups_page<C>(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    paginator: Paginator,
+    on_page: C,
+) -> Result<u64, AppError>
+where
+    C: FnMut(Page<SerializedGroup>) -> Result<(), AppError>,
+{
+    paginator
+        .for_each_page(
+            |offset, limit| {
+                let params = ListParams {
+                    offset: Some(offset),
+                    limit: Some(limit),
+                    ..params.clone()
+                };
+
+                async move {
+                    let groups =
+                        get_groups_page(state, profile, params, CachePolicy::ReadOnly).await?;
+                    Ok(Page::new(groups.range.total, groups.items.clone()))
+                }
+            },
+            on_page,
+        )
+        .await
+}
+
+/// Fetches all pages of users of a group and passes them on in order - cached pages are reused, fetched pages are not cached
+pub(crate) async fn for_each_group_users_page<C>(
+    state: &AppState,
+    profile: &Profile,
+    group_id: u64,
+    params: ListParams,
+    paginator: Paginator,
+    on_page: C,
+) -> Result<u64, AppError>
+where
+    C: FnMut(Page<SerializedGroupUser>) -> Result<(), AppError>,
+{
+    paginator
+        .for_each_page(
+            |offset, limit| {
+                let params = ListParams {
+                    offset: Some(offset),
+                    limit: Some(limit),
+                    ..params.clone()
+                };
+
+                async move {
+                    let group_users = get_group_users_page(
+                        state,
+                        profile,
+                        group_id,
+                        params,
+                        CachePolicy::ReadOnly,
+                    )
+                    .await?;
+                    Ok(Page::new(
+                        group_users.range.total,
+                        group_users.items.clone(),
+                    ))
+                }
+            },
+            on_page,
+        )
+        .await
+}
+
+/// Fetches all pages of groups matching the params
+pub(crate) async fn get_all_groups(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    paginator: Paginator,
+) -> Result<Vec<SerializedGroup>, AppError> {
+    let mut groups = Vec::new();
+
+    for_each_groups_page(state, profile, params, paginator, |page| {
+        groups.extend(page.items);
+        Ok(())
+    })
+    .await?;
+
+    Ok(groups)
+}
+
+/// Fetches all pages of users of a group
+pub(crate) async fn get_all_group_users(
+    state: &AppState,
+    profile: &Profile,
+    group_id: u64,
+    params: ListParams,
+    paginator: Paginator,
+) -> Result<Vec<SerializedGroupUser>, AppError> {
+    let mut group_users = Vec::new();
+
+    for_each_group_users_page(state, profile, group_id, params, paginator, |page| {
+        group_users.extend(page.items);
+        Ok(())
+    })
+    .await?;
+
+    Ok(group_users)
+}
@@ -1,9 +1,102 @@
+use std::hash::Hash;
+
 use dco3::groups::{Group, GroupList, GroupUser, GroupUserList};
 use serde::Serialize;
 
-use crate::{models::Range, users::SerializedRoleList, ROLE_AUDITOR, ROLE_CONFIG_MANAGER, ROLE_GROUP_MANAGER, ROLE_ROOM_MANAGER, ROLE_USER_MANAGER};
+use crate::{cache::CacheKey, models::{ListParams, Range}, users::SerializedRoleList, ROLE_AUDITOR, ROLE_CONFIG_MANAGER, ROLE_GROUP_MANAGER, ROLE_ROOM_MANAGER, ROLE_USER_MANAGER};
 
-#[derive(Serialize)]
+#[derive(PartialEq, Eq)]
+pub struct GroupsCacheKey {
+    url: String,
+    params: ListParams
+}
+
+impl GroupsCacheKey {
+    pub fn new(url: String, params: ListParams) -> Self {
+        Self {
+            url,
+            params
+        }
+    }
+}
+
+impl From<&GroupsCacheKey> for String {
+    fn from(value: &GroupsCacheKey) -> Self {
+        format!("{}{}", value.url, value.params.to_string())
+    }
+}
+
+impl CacheKey for GroupsCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Hash for GroupsCacheKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let key: String = self.into();
+        key.hash(state);
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+pub struct GroupCacheKey {
+    url: String,
+    group_id: u64
+}
+
+impl GroupCacheKey {
+    pub fn new(url: String, group_id: u64) -> Self {
+        Self {
+            url,
+            group_id
+        }
+    }
+}
+
+impl CacheKey for GroupCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(PartialEq, Eq)]
+pub struct GroupUsersCacheKey {
+    url: String,
+    group_id: u64,
+    params: ListParams
+}
+
+impl GroupUsersCacheKey {
+    pub fn new(url: String, group_id: u64, params: ListParams) -> Self {
+        Self {
+            url,
+            group_id,
+            params
+        }
+    }
+}
+
+impl From<&GroupUsersCacheKey> for String {
+    fn from(value: &GroupUsersCacheKey) -> Self {
+        format!("{}{}{}", value.url, value.group_id, value.params.to_string())
+    }
+}
+
+impl CacheKey for GroupUsersCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Hash for GroupUsersCacheKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let key: String = self.into();
+        key.hash(state);
+    }
+}
+
+#[derive(Serialize, Clone)]
 pub struct SerializedGroupInfo {
     pub id: u64,
     pub name: String,
@@ -20,13 +113,13 @@ impl From<Group> for SerializedGroupInfo {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct SerializedGroupList {
     pub items: Vec<SerializedGroup>,
     pub range: Range,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedGroup {
     pub id: u64,
@@ -160,13 +253,13 @@ impl From<GroupList> for SerializedGroupList {
 }
 
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct SerializedGroupUserList {
     pub items: Vec<SerializedGroupUser>,
     pub range: Range,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedGroupUser {
     pub id: i64,
@@ -188,16 +281,16 @@ pub struct SerializedGroupUserWithGroupInfo {
     pub group_name: String,
 }
 
-impl From<(Group, GroupUser)> for SerializedGroupUserWithGroupInfo {
-    fn from((group, user): (Group, GroupUser)) -> Self {
+impl From<(&SerializedGroup, SerializedGroupUser)> for SerializedGroupUserWithGroupInfo {
+    fn from((group, user): (&SerializedGroup, SerializedGroupUser)) -> Self {
         SerializedGroupUserWithGroupInfo {
-            id: user.user_info.id,
-            first_name: user.user_info.first_name,
-            last_name: user.user_info.last_name,
-            email: user.user_info.email,
-            user_name: user.user_info.user_name,
+            id: user.id,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            email: user.email,
+            user_name: user.user_name,
             group_id: group.id,
-            group_name: group.name,
+            group_name: group.name.clone(),
         }
     }
 }
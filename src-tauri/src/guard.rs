@@ -0,0 +1,94 @@
+use crate::{
+    error::AppError, models::AppState, ROLE_AUDITOR, ROLE_GROUP_MANAGER, ROLE_USER_MANAGER,
+};
+
+const AUDIT_ROLES: &[&str] = &[ROLE_AUDITOR];
+// users and groups can be read by user and group managers
+const USER_ROLES: &[&str] = &[ROLE_USER_MANAGER, ROLE_GROUP_MANAGER];
+// user search is also used on auditor pages (e.g. permissions of a user)
+const USER_SEARCH_ROLES: &[&str] = &[ROLE_USER_MANAGER, ROLE_GROUP_MANAGER, ROLE_AUDITOR];
+
+/// Commands restricted to certain roles - commands not listed here are available to every user
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuardedCommand {
+    GetEvents,
+    GetOperationTypes,
+    ExportEvents,
+    GetPermissions,
+    ExportUserPermissions,
+    ExportAllUserPermissions,
+    GetUsers,
+    ExportUsers,
+    GetGroup,
+    GetGroups,
+    ExportGroups,
+    GetGroupUsers,
+    ExportGroupUsers,
+    ExportAllGroupUsers,
+}
+
+impl GuardedCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GuardedCommand::GetEvents => "get_events",
+            GuardedCommand::GetOperationTypes => "get_operation_types",
+            GuardedCommand::ExportEvents => "export_events",
+            GuardedCommand::GetPermissions => "get_permissions",
+            GuardedCommand::ExportUserPermissions => "export_user_permissions",
+            GuardedCommand::ExportAllUserPermissions => "export_all_user_permissions",
+            GuardedCommand::GetUsers => "get_users",
+            GuardedCommand::ExportUsers => "export_users",
+            GuardedCommand::GetGroup => "get_group",
+            GuardedCommand::GetGroups => "get_groups",
+            GuardedCommand::ExportGroups => "export_groups",
+            GuardedCommand::GetGroupUsers => "get_group_users",
+            GuardedCommand::ExportGroupUsers => "export_group_users",
+            GuardedCommand::ExportAllGroupUsers => "export_all_group_users",
+        }
+    }
+
+    /// Roles required for the command - the user needs at least one of them
+    fn required_roles(&self) -> &'static [&'static str] {
+        match self {
+            GuardedCommand::GetEvents
+            | GuardedCommand::GetOperationTypes
+            | GuardedCommand::ExportEvents
+            | GuardedCommand::GetPermissions
+            | GuardedCommand::ExportUserPermissions
+            | GuardedCommand::ExportAllUserPermissions => AUDIT_ROLES,
+            GuardedCommand::GetUsers => USER_SEARCH_ROLES,
+            GuardedCommand::ExportUsers
+            | GuardedCommand::GetGroup
+            | GuardedCommand::GetGroups
+            | GuardedCommand::ExportGroups
+            | GuardedCommand::GetGroupUsers
+            | GuardedCommand::ExportGroupUsers
+            | GuardedCommand::ExportAllGroupUsers => USER_ROLES,
+        }
+    }
+}
+
+/// Rejects a command early if the connected user lacks the required roles (cached on connect)
+pub async fn require_roles(state: &AppState, command: GuardedCommand) -> Result<(), AppError> {
+    let required = command.required_roles();
+
+    let roles = state
+        .active_profile()
+        .await?
+        .roles()
+        .await
+        .ok_or(AppError::NotConnected)?;
+
+    if required.iter().any(|role| roles.iter().any(|r| r == role)) {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Rejected {} - missing role {}",
+        command.name(),
+        required.join(" or ")
+    );
+    Err(AppError::MissingRole(
+        required.iter().map(|role| role.to_string()).collect(),
+    ))
+}
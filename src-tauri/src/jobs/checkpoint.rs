@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, settings::ExportFormat};
+
+use super::models::ExportRequest;
+
+const CHECKPOINT_EXTENSION: &str = "checkpoint.json";
+
+/// Progress of an export - written next to the export file after a page or user / group
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportCheckpoint {
+    pub job_id: String,
+    pub request: ExportRequest,
+    /// profile and url the export was written with - a resume must use the same
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub url: String,
+    pub format: ExportFormat,
+    /// rows written up to the checkpoint
+    pub rows: u64,
+    /// size of the export file at the checkpoint - content after it is dropped on resume
+    pub bytes: u64,
+    /// offset of the next page (paged lists)
+    pub offset: u64,
+    /// last user (all permissions) or group (all group users) written completely
+    pub last_id: Option<u64>,
+    pub updated_at: String,
+}
+
+impl ExportCheckpoint {
+    /// Reads the checkpoint of an export file
+    pub fn load(export_path: &str) -> Result<Option<Self>, AppError> {
+        let path = checkpoint_path(export_path);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let checkpoint = serde_json::from_str(&content)?;
+
+        Ok(Some(checkpoint))
+    }
+
+    pub fn save(&mut self, export_path: &str) -> Result<(), AppError> {
+        self.updated_at = Utc::now().to_rfc3339();
+
+        let path = checkpoint_path(export_path);
+        let tmp_path = path.with_extension("tmp");
+        let content = serde_json::to_string(self)?;
+
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        Ok(())
+    }
+
+    /// Checkpoints of older versions have no profile and never match
+    pub fn matches_profile(&self, name: &str, url: &str) -> bool {
+        !self.profile.is_empty() && self.profile == name && self.url == url
+    }
+
+    pub fn remove(export_path: &str) {
+        let path = checkpoint_path(export_path);
+
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!("Error removing checkpoint {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// `<export file>.checkpoint.json`
+fn checkpoint_path(export_path: &str) -> PathBuf {
+    PathBuf::from(format!("{export_path}.{CHECKPOINT_EXTENSION}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::models::ListParams;
+
+    fn checkpoint() -> ExportCheckpoint {
+        ExportCheckpoint {
+            job_id: "20240101-120000-abcd".to_string(),
+            request: ExportRequest::GroupUsers {
+                group_id: 42,
+                params: ListParams {
+                    filter: Some("userName:cn:doe".to_string()),
+                    ..Default::default()
+                },
+            },
+            profile: "production".to_string(),
+            url: "https://dracoon.example.com".to_string(),
+            format: ExportFormat::Csv,
+            rows: 1500,
+            bytes: 123_456,
+            offset: 1500,
+            last_id: Some(7),
+            updated_at: String::new(),
+        }
+    }
+
+    /// Export path in a temp directory - removed when the directory is dropped
+    fn export_path(dir: &TempDir) -> String {
+        dir.path().join("export.csv").to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export_path(&dir);
+        let mut saved = checkpoint();
+
+        saved.save(&path).unwrap();
+        let loaded = ExportCheckpoint::load(&path).unwrap().unwrap();
+
+        assert!(!saved.updated_at.is_empty());
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&saved).unwrap()
+        );
+        assert!(!checkpoint_path(&path).with_extension("tmp").exists());
+
+        ExportCheckpoint::remove(&path);
+        assert!(ExportCheckpoint::load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export_path(&dir);
+
+        assert!(ExportCheckpoint::load(&path).unwrap().is_none());
+        // removing a missing checkpoint is not an error
+        ExportCheckpoint::remove(&path);
+    }
+
+    #[test]
+    fn test_matches_profile() {
+        let checkpoint = checkpoint();
+
+        assert!(checkpoint.matches_profile("production", "https://dracoon.example.com"));
+        assert!(!checkpoint.matches_profile("staging", "https://dracoon.example.com"));
+        assert!(!checkpoint.matches_profile("production", "https://other.example.com"));
+    }
+
+    #[test]
+    fn test_checkpoint_without_profile_does_not_match() {
+        let mut value = serde_json::to_value(checkpoint()).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("profile");
+        object.remove("url");
+
+        let checkpoint: ExportCheckpoint = serde_json::from_value(value).unwrap();
+
+        assert!(!checkpoint.matches_profile("", ""));
+        assert!(!checkpoint.matches_profile("production", "https://dracoon.example.com"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{sync::Arc, time::Instant};
+
+use tauri::{Manager, State};
+
+use crate::{
+    error::AppError, events, groups, guard::require_roles, models::Profile, permissions, users,
+    AppState,
+};
+use checkpoint::ExportCheckpoint;
+use models::{JobStatus, SerializedJob};
+
+pub use models::{ExportRequest, Job, JobManager};
+
+mod checkpoint;
+mod models;
+
+/// Queues an export - it runs in the background once a slot is free. Returns the job id.
+pub(crate) async fn enqueue_export(
+    state: &AppState,
+    request: ExportRequest,
+    path: String,
+) -> Result<String, AppError> {
+    let profile = state.active_profile().await?;
+    enqueue_job(state, profile, request, path, None).await
+}
+
+async fn enqueue_job(
+    state: &AppState,
+    profile: Arc<Profile>,
+    request: ExportRequest,
+    path: String,
+    resume: Option<ExportCheckpoint>,
+) -> Result<String, AppError> {
+    let app_handle = state
+        .app_handle()
+        .ok_or_else(|| AppError::Internal("App not initialized".to_string()))?;
+    let jobs = state.jobs();
+
+    let mut job = jobs.enqueue(
+        request.clone(),
+        path.clone(),
+        profile,
+        Some(app_handle.clone()),
+        resume,
+    );
+    let job_id = job.id().to_string();
+
+    tauri::async_runtime::spawn(async move {
+        let _slot = jobs.acquire().await;
+
+        if job.start().is_err() {
+            tracing::info!("Skipping cancelled job {}", job.id());
+            return;
+        }
+
+        let state = app_handle.state::<AppState>();
+        let now = Instant::now();
+
+        let result = run_export(&state, &request, &mut job).await;
+
+        let elapsed = now.elapsed().as_millis();
+        state.record_timing(request.command().name(), elapsed, result.is_err());
+        job.finish(&result);
+    });
+
+    Ok(job_id)
+}
+
+/// Writes the export - returns the number of written rows
+async fn run_export(
+    state: &AppState,
+    request: &ExportRequest,
+    job: &mut Job,
+) -> Result<u64, AppError> {
+    match request {
+        ExportRequest::Users(params) => users::write_users(state, params.clone(), job).await,
+        ExportRequest::Groups(params) => groups::write_groups(state, params.clone(), job).await,
+        ExportRequest::GroupUsers { group_id, params } => {
+            groups::write_group_users(state, *group_id, params.clone(), job).await
+        }
+        ExportRequest::AllGroupUsers => groups::write_all_group_users(state, job).await,
+        ExportRequest::Events(params) => events::write_events(state, params.clone(), job).await,
+        ExportRequest::UserPermissions {
+            params,
+            force_refresh,
+        } => permissions::write_user_permissions(state, params.clone(), *force_refresh, job).await,
+        ExportRequest::AllUserPermissions => {
+            permissions::write_all_user_permissions(state, job).await
+        }
+    }
+}
+
+/// Queued, running and finished jobs - newest first
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<SerializedJob>, AppError> {
+    Ok(state.jobs().list())
+}
+
+#[tauri::command]
+pub async fn get_job(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<SerializedJob, AppError> {
+    state.jobs().get(&job_id)
+}
+
+/// Continues a failed export from its checkpoint - returns the id of the new job
+#[tauri::command]
+pub async fn resume_job(job_id: String, state: State<'_, AppState>) -> Result<String, AppError> {
+    let job = state.jobs().get(&job_id)?;
+    require_roles(&state, job.request.command()).await?;
+
+    if job.status != JobStatus::Failed {
+        return Err(AppError::InvalidInput(format!(
+            "Job {job_id} did not fail - only failed exports can be resumed"
+        )));
+    }
+
+    let checkpoint = ExportCheckpoint::load(&job.path)?
+        .filter(|checkpoint| checkpoint.job_id == job_id)
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "No checkpoint found for job {job_id} - the export needs to be restarted"
+            ))
+        })?;
+
+    // the export continues against the profile it was written with
+    let profile = state.active_profile().await?;
+    if !checkpoint.matches_profile(profile.name(), profile.url()) {
+        return Err(AppError::InvalidInput(format!(
+            "Job {job_id} was exported with profile {} ({}) - connect with it to resume",
+            checkpoint.profile, checkpoint.url
+        )));
+    }
+
+    tracing::info!(
+        "Resuming job {job_id} after {} rows (offset {}, last id {:?})",
+        checkpoint.rows,
+        checkpoint.offset,
+        checkpoint.last_id
+    );
+
+    enqueue_job(
+        &state,
+        profile,
+        checkpoint.request.clone(),
+        job.path,
+        Some(checkpoint),
+    )
+    .await
+}
+
+/// Stops a queued or running export - the incomplete file is removed
+#[tauri::command]
+pub async fn cancel_job(job_id: String, state: State<'_, AppState>) -> Result<(), AppError> {
+    state.jobs().cancel(&job_id, state.app_handle())?;
+    tracing::info!("Cancelling job {job_id}");
+
+    Ok(())
+}
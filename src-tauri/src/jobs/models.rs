@@ -0,0 +1,746 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    error::AppError,
+    events::EventListParams,
+    export::ExportWriter,
+    guard::GuardedCommand,
+    models::{ListParams, Profile},
+    settings::ExportFormat,
+};
+
+use super::checkpoint::ExportCheckpoint;
+
+pub const JOB_PROGRESS_EVENT: &str = "job-progress";
+const JOBS_FILE: &str = "jobs.json";
+// finished jobs kept in the history - the oldest are dropped first
+const MAX_JOB_HISTORY: usize = 200;
+// progress events are throttled - the final state is always emitted
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+// checkpoints are written at most once per interval - a resume repeats the pages or users after it
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobKind {
+    Users,
+    Groups,
+    GroupUsers,
+    AllGroupUsers,
+    Events,
+    UserPermissions,
+    AllUserPermissions,
+}
+
+/// An export with its params - stored in the job history
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "params", rename_all = "camelCase")]
+pub enum ExportRequest {
+    Users(ListParams),
+    Groups(ListParams),
+    #[serde(rename_all = "camelCase")]
+    GroupUsers {
+        group_id: u64,
+        params: ListParams,
+    },
+    AllGroupUsers,
+    Events(EventListParams),
+    #[serde(rename_all = "camelCase")]
+    UserPermissions {
+        params: ListParams,
+        force_refresh: bool,
+    },
+    AllUserPermissions,
+}
+
+impl ExportRequest {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            ExportRequest::Users(_) => JobKind::Users,
+            ExportRequest::Groups(_) => JobKind::Groups,
+            ExportRequest::GroupUsers { .. } => JobKind::GroupUsers,
+            ExportRequest::AllGroupUsers => JobKind::AllGroupUsers,
+            ExportRequest::Events(_) => JobKind::Events,
+            ExportRequest::UserPermissions { .. } => JobKind::UserPermissions,
+            ExportRequest::AllUserPermissions => JobKind::AllUserPermissions,
+        }
+    }
+
+    /// Command that queued the export - used for role checks and timings
+    pub fn command(&self) -> GuardedCommand {
+        match self {
+            ExportRequest::Users(_) => GuardedCommand::ExportUsers,
+            ExportRequest::Groups(_) => GuardedCommand::ExportGroups,
+            ExportRequest::GroupUsers { .. } => GuardedCommand::ExportGroupUsers,
+            ExportRequest::AllGroupUsers => GuardedCommand::ExportAllGroupUsers,
+            ExportRequest::Events(_) => GuardedCommand::ExportEvents,
+            ExportRequest::UserPermissions { .. } => GuardedCommand::ExportUserPermissions,
+            ExportRequest::AllUserPermissions => GuardedCommand::ExportAllUserPermissions,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedJobError {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&AppError> for SerializedJobError {
+    fn from(value: &AppError) -> Self {
+        SerializedJobError {
+            code: value.code().to_string(),
+            message: value.to_string(),
+        }
+    }
+}
+
+/// A queued, running or finished job
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedJob {
+    pub id: String,
+    pub status: JobStatus,
+    pub request: ExportRequest,
+    pub path: String,
+    /// profile active when the job was queued
+    pub profile: Option<String>,
+    /// written rows - set once the job finished
+    pub rows: Option<u64>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub error: Option<SerializedJobError>,
+    /// failed job continued by this job
+    pub resumed_from: Option<String>,
+}
+
+/// Payload of the `job-progress` event
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedJobProgress {
+    pub job_id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub pages_fetched: u64,
+    /// users (permissions) or groups (group users) processed
+    pub processed: u64,
+    pub total: Option<u64>,
+    pub rows_written: u64,
+    /// rows of paged exports (users, groups, events) - known after the first page
+    pub total_rows: Option<u64>,
+    pub elapsed_ms: u64,
+    pub eta_secs: Option<u64>,
+}
+
+impl SerializedJobProgress {
+    fn new(job_id: &str, kind: JobKind, status: JobStatus) -> Self {
+        SerializedJobProgress {
+            job_id: job_id.to_string(),
+            kind,
+            status,
+            pages_fetched: 0,
+            processed: 0,
+            total: None,
+            rows_written: 0,
+            total_rows: None,
+            elapsed_ms: 0,
+            eta_secs: None,
+        }
+    }
+}
+
+struct JobRecord {
+    job: SerializedJob,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Job history and slots for running jobs - the history is stored in `jobs.json`
+#[derive(Clone)]
+pub struct JobManager {
+    jobs: Arc<Mutex<Vec<JobRecord>>>,
+    // history to write - written on a background thread to never block the async runtime
+    history_writer: Arc<Mutex<mpsc::Sender<String>>>,
+    slots: Arc<Semaphore>,
+}
+
+impl JobManager {
+    /// Loads the job history - jobs that did not finish before the app was closed are marked as failed
+    pub fn load(config_dir: &Path, max_running: usize) -> Self {
+        let path = config_dir.join(JOBS_FILE);
+
+        let history: Vec<SerializedJob> = if path.exists() {
+            std::fs::read_to_string(&path)
+                .map_err(AppError::from)
+                .and_then(|content| serde_json::from_str(&content).map_err(AppError::from))
+                .unwrap_or_else(|e| {
+                    tracing::error!("Error loading job history: {e}");
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+
+        let jobs = history
+            .into_iter()
+            .map(|mut job| {
+                if !job.status.is_finished() {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(SerializedJobError::from(&AppError::Internal(
+                        "Interrupted - the app was closed".to_string(),
+                    )));
+                }
+
+                JobRecord {
+                    job,
+                    cancelled: Arc::new(AtomicBool::new(false)),
+                }
+            })
+            .collect();
+
+        JobManager {
+            jobs: Arc::new(Mutex::new(jobs)),
+            history_writer: Arc::new(Mutex::new(spawn_history_writer(path))),
+            slots: Arc::new(Semaphore::new(max_running.max(1))),
+        }
+    }
+
+    /// All jobs - newest first
+    pub fn list(&self) -> Vec<SerializedJob> {
+        self.lock()
+            .iter()
+            .rev()
+            .map(|record| record.job.clone())
+            .collect()
+    }
+
+    pub fn get(&self, job_id: &str) -> Result<SerializedJob, AppError> {
+        self.lock()
+            .iter()
+            .find(|record| record.job.id == job_id)
+            .map(|record| record.job.clone())
+            .ok_or_else(|| AppError::InvalidInput(format!("Job {job_id} not found")))
+    }
+
+    /// Adds a job to the history - a job resuming from a checkpoint takes over the checkpoint.
+    /// The job runs against the profile it was queued with, even if another profile is activated meanwhile.
+    pub fn enqueue(
+        &self,
+        request: ExportRequest,
+        path: String,
+        profile: Arc<Profile>,
+        app_handle: Option<AppHandle>,
+        resume: Option<ExportCheckpoint>,
+    ) -> Job {
+        let id = format!(
+            "{}-{:04x}",
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            rand::random::<u16>()
+        );
+        let kind = request.kind();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let resumed_from = resume.as_ref().map(|checkpoint| checkpoint.job_id.clone());
+
+        {
+            let mut jobs = self.lock();
+            jobs.push(JobRecord {
+                job: SerializedJob {
+                    id: id.clone(),
+                    status: JobStatus::Queued,
+                    request: request.clone(),
+                    path: path.clone(),
+                    profile: Some(profile.name().to_string()),
+                    rows: None,
+                    created_at: Utc::now().to_rfc3339(),
+                    started_at: None,
+                    finished_at: None,
+                    duration_ms: None,
+                    error: None,
+                    resumed_from: resumed_from.clone(),
+                },
+                cancelled: cancelled.clone(),
+            });
+
+            let finished = jobs
+                .iter()
+                .filter(|record| record.job.status.is_finished())
+                .count();
+            if finished > MAX_JOB_HISTORY {
+                if let Some(oldest) = jobs
+                    .iter()
+                    .position(|record| record.job.status.is_finished())
+                {
+                    jobs.remove(oldest);
+                }
+            }
+
+            self.save(&jobs);
+        }
+
+        let resume = resume.map(|mut checkpoint| {
+            checkpoint.job_id = id.clone();
+            if let Err(e) = checkpoint.save(&path) {
+                tracing::error!("Error saving checkpoint: {e}");
+            }
+            checkpoint
+        });
+
+        match resumed_from {
+            Some(resumed_from) => {
+                tracing::info!("Queued job {id} ({kind:?}) resuming {resumed_from}")
+            }
+            None => tracing::info!("Queued job {id} ({kind:?})"),
+        }
+
+        let job = Job {
+            id,
+            kind,
+            request,
+            path,
+            profile,
+            checkpointed: resume.is_some(),
+            resume,
+            format: None,
+            offset: 0,
+            last_id: None,
+            last_checkpoint: None,
+            cancelled,
+            manager: self.clone(),
+            app_handle,
+            started: Instant::now(),
+            last_emitted: None,
+            pages_fetched: 0,
+            processed: 0,
+            total: None,
+            rows_written: 0,
+            start_rows: 0,
+            total_rows: None,
+        };
+
+        job.emit(JobStatus::Queued);
+        job
+    }
+
+    /// Waits for a free slot - the number of running jobs is limited
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("job slots closed")
+    }
+
+    /// Flags a job - a running job stops before fetching the next page, a queued job is not started
+    pub fn cancel(&self, job_id: &str, app_handle: Option<AppHandle>) -> Result<(), AppError> {
+        let mut jobs = self.lock();
+        let record = jobs
+            .iter_mut()
+            .find(|record| record.job.id == job_id)
+            .ok_or_else(|| AppError::InvalidInput(format!("Job {job_id} not found")))?;
+
+        if record.job.status.is_finished() {
+            return Err(AppError::InvalidInput(format!(
+                "Job {job_id} already finished"
+            )));
+        }
+
+        record.cancelled.store(true, Ordering::Relaxed);
+
+        if record.job.status == JobStatus::Queued {
+            record.job.status = JobStatus::Cancelled;
+            record.job.finished_at = Some(Utc::now().to_rfc3339());
+            record.job.error = Some(SerializedJobError::from(&AppError::Cancelled));
+            let progress =
+                SerializedJobProgress::new(job_id, record.job.request.kind(), JobStatus::Cancelled);
+            self.save(&jobs);
+            emit_progress(app_handle.as_ref(), progress);
+        }
+
+        Ok(())
+    }
+
+    fn update<F: FnOnce(&mut SerializedJob)>(&self, job_id: &str, f: F) {
+        let mut jobs = self.lock();
+
+        if let Some(record) = jobs.iter_mut().find(|record| record.job.id == job_id) {
+            f(&mut record.job);
+            self.save(&jobs);
+        }
+    }
+
+    /// Passes the history to the writer thread - called with the lock held to keep writes in order
+    fn save(&self, jobs: &[JobRecord]) {
+        let history: Vec<&SerializedJob> = jobs.iter().map(|record| &record.job).collect();
+
+        let content = match serde_json::to_string_pretty(&history) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::error!("Error serializing job history: {e}");
+                return;
+            }
+        };
+
+        let sent = self
+            .history_writer
+            .lock()
+            .expect("job history writer lock poisoned")
+            .send(content);
+
+        if sent.is_err() {
+            tracing::error!("Error saving job history: writer stopped");
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<JobRecord>> {
+        self.jobs.lock().expect("jobs lock poisoned")
+    }
+}
+
+/// Writes the job history on a background thread - only the latest of several pending histories is written
+fn spawn_history_writer(path: PathBuf) -> mpsc::Sender<String> {
+    let (sender, receiver) = mpsc::channel::<String>();
+
+    let spawned = std::thread::Builder::new()
+        .name("job-history".to_string())
+        .spawn(move || {
+            while let Ok(mut content) = receiver.recv() {
+                while let Ok(newer) = receiver.try_recv() {
+                    content = newer;
+                }
+
+                let tmp_path = path.with_extension("tmp");
+                let result = std::fs::write(&tmp_path, content)
+                    .and_then(|_| std::fs::rename(&tmp_path, &path));
+
+                if let Err(e) = result {
+                    tracing::error!("Error saving job history: {e}");
+                }
+            }
+        });
+
+    if let Err(e) = spawned {
+        tracing::error!("Error starting job history writer: {e}");
+    }
+
+    sender
+}
+
+/// A queued or running job - tracks progress and updates the job history
+pub struct Job {
+    id: String,
+    kind: JobKind,
+    request: ExportRequest,
+    path: String,
+    profile: Arc<Profile>,
+    /// checkpoint of a failed job this job continues
+    resume: Option<ExportCheckpoint>,
+    format: Option<ExportFormat>,
+    offset: u64,
+    last_id: Option<u64>,
+    last_checkpoint: Option<Instant>,
+    checkpointed: bool,
+    cancelled: Arc<AtomicBool>,
+    manager: JobManager,
+    app_handle: Option<AppHandle>,
+    started: Instant,
+    last_emitted: Option<Instant>,
+    pages_fetched: u64,
+    processed: u64,
+    total: Option<u64>,
+    rows_written: u64,
+    /// rows written before a resumed checkpoint
+    start_rows: u64,
+    total_rows: Option<u64>,
+}
+
+impl Job {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Profile the job was queued with
+    pub fn profile(&self) -> Arc<Profile> {
+        self.profile.clone()
+    }
+
+    /// Returns `AppError::Cancelled` once the job was cancelled
+    pub fn check_cancelled(&self) -> Result<(), AppError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(AppError::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the job as running - fails if it was cancelled while queued
+    pub fn start(&mut self) -> Result<(), AppError> {
+        if let Err(e) = self.check_cancelled() {
+            // a cancelled resume gives up the incomplete export
+            if self.resume.is_some() {
+                self.remove_files();
+            }
+            return Err(e);
+        }
+        self.started = Instant::now();
+
+        self.manager.update(&self.id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now().to_rfc3339());
+        });
+
+        tracing::info!("Started job {} ({:?})", self.id, self.kind);
+        self.emit(JobStatus::Running);
+        Ok(())
+    }
+
+    pub fn set_total(&mut self, total: u64) {
+        self.total = Some(total);
+        self.report();
+    }
+
+    pub fn page_fetched(&mut self, rows_written: u64) -> Result<(), AppError> {
+        self.pages_fetched += 1;
+        self.rows_written = rows_written;
+        self.report();
+        self.check_cancelled()
+    }
+
+    fn item_processed(&mut self, rows_written: u64) -> Result<(), AppError> {
+        self.processed += 1;
+        self.rows_written = rows_written;
+        self.report();
+        self.check_cancelled()
+    }
+
+    /// Offset of the first page to fetch - after the checkpoint when resuming
+    pub fn resume_offset(&self) -> u64 {
+        self.resume
+            .as_ref()
+            .map_or(0, |checkpoint| checkpoint.offset)
+    }
+
+    /// Last user or group written completely before the checkpoint
+    pub fn resume_last_id(&self) -> Option<u64> {
+        self.resume
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.last_id)
+    }
+
+    /// Creates the export file - a resumed job appends to the file of the checkpoint in its format
+    pub fn writer(&mut self, format: ExportFormat) -> Result<ExportWriter, AppError> {
+        let writer = match &self.resume {
+            Some(checkpoint) => {
+                tracing::info!(
+                    "Resuming export {} after {} rows",
+                    self.path,
+                    checkpoint.rows
+                );
+                self.offset = checkpoint.offset;
+                self.last_id = checkpoint.last_id;
+                ExportWriter::append(
+                    &self.path,
+                    checkpoint.format,
+                    checkpoint.rows,
+                    checkpoint.bytes,
+                )?
+            }
+            None => ExportWriter::create(&self.path, format)?,
+        };
+
+        self.format = Some(
+            self.resume
+                .as_ref()
+                .map_or(format, |checkpoint| checkpoint.format),
+        );
+        self.rows_written = writer.written();
+        self.start_rows = writer.written();
+        Ok(writer)
+    }
+
+    /// A page was written - `next_offset` is stored in the checkpoint, `total_rows` is the total of the list
+    pub fn page_written(
+        &mut self,
+        writer: &mut ExportWriter,
+        next_offset: u64,
+        total_rows: u64,
+    ) -> Result<(), AppError> {
+        self.offset = next_offset;
+        self.total_rows = Some(total_rows);
+        self.checkpoint(writer)?;
+        self.page_fetched(writer.written())
+    }
+
+    /// All rows of a user or group were written - the id is stored in the checkpoint
+    pub fn item_written(&mut self, writer: &mut ExportWriter, id: u64) -> Result<(), AppError> {
+        self.last_id = Some(id);
+        self.checkpoint(writer)?;
+        self.item_processed(writer.written())
+    }
+
+    /// Saves the checkpoint if the last one is older than the checkpoint interval
+    fn checkpoint(&mut self, writer: &mut ExportWriter) -> Result<(), AppError> {
+        let due = self
+            .last_checkpoint
+            .map_or(true, |last| last.elapsed() >= CHECKPOINT_INTERVAL);
+
+        if due {
+            self.save_checkpoint(writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn save_checkpoint(&mut self, writer: &mut ExportWriter) -> Result<(), AppError> {
+        let format = self
+            .format
+            .ok_or_else(|| AppError::Internal("Export file not created".to_string()))?;
+        let bytes = writer.flush()?;
+
+        ExportCheckpoint {
+            job_id: self.id.clone(),
+            request: self.request.clone(),
+            profile: self.profile.name().to_string(),
+            url: self.profile.url().to_string(),
+            format,
+            rows: writer.written(),
+            bytes,
+            offset: self.offset,
+            last_id: self.last_id,
+            updated_at: String::new(),
+        }
+        .save(&self.path)?;
+
+        self.last_checkpoint = Some(Instant::now());
+        self.checkpointed = true;
+        Ok(())
+    }
+
+    /// Removes the incomplete export and its checkpoint
+    fn remove_files(&self) {
+        ExportCheckpoint::remove(&self.path);
+
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!("Error removing incomplete export: {e}");
+            }
+        }
+    }
+
+    /// Stores the result in the job history and emits the final state
+    pub fn finish(self, result: &Result<u64, AppError>) {
+        let status = match result {
+            Ok(_) => JobStatus::Completed,
+            Err(AppError::Cancelled) => JobStatus::Cancelled,
+            Err(_) => JobStatus::Failed,
+        };
+        let elapsed = self.started.elapsed().as_millis();
+
+        self.manager.update(&self.id, |job| {
+            job.status = status;
+            job.rows = Some(*result.as_ref().unwrap_or(&self.rows_written));
+            job.finished_at = Some(Utc::now().to_rfc3339());
+            job.duration_ms = Some(elapsed as u64);
+            job.error = result.as_ref().err().map(SerializedJobError::from);
+        });
+
+        match status {
+            JobStatus::Completed => ExportCheckpoint::remove(&self.path),
+            JobStatus::Cancelled => self.remove_files(),
+            _ => {
+                if self.checkpointed {
+                    tracing::info!("Job {} can be resumed from its checkpoint", self.id);
+                }
+            }
+        }
+
+        tracing::info!("Job {} finished in {elapsed} ms: {status:?}", self.id);
+        self.emit(status);
+    }
+
+    fn report(&mut self) {
+        let due = self
+            .last_emitted
+            .map_or(true, |last| last.elapsed() >= PROGRESS_INTERVAL);
+
+        if due {
+            self.last_emitted = Some(Instant::now());
+            self.emit(JobStatus::Running);
+        }
+    }
+
+    /// Estimated from the average duration per processed item (users, groups) -
+    /// or per written row for paged exports
+    fn eta_secs(&self) -> Option<u64> {
+        let (done, remaining) = match (self.total, self.total_rows) {
+            (Some(total), _) => (self.processed, total.saturating_sub(self.processed)),
+            // rows before a resumed checkpoint were not written by this job
+            (None, Some(total_rows)) => (
+                self.rows_written.saturating_sub(self.start_rows),
+                total_rows.saturating_sub(self.rows_written),
+            ),
+            (None, None) => return None,
+        };
+
+        if done == 0 {
+            return None;
+        }
+
+        let per_item = self.started.elapsed().as_secs_f64() / done as f64;
+        Some((per_item * remaining as f64).round() as u64)
+    }
+
+    fn progress(&self, status: JobStatus) -> SerializedJobProgress {
+        SerializedJobProgress {
+            pages_fetched: self.pages_fetched,
+            processed: self.processed,
+            total: self.total,
+            rows_written: self.rows_written,
+            total_rows: self.total_rows,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            eta_secs: self.eta_secs(),
+            ..SerializedJobProgress::new(&self.id, self.kind, status)
+        }
+    }
+
+    fn emit(&self, status: JobStatus) {
+        emit_progress(self.app_handle.as_ref(), self.progress(status));
+    }
+}
+
+fn emit_progress(app_handle: Option<&AppHandle>, progress: SerializedJobProgress) {
+    if let Some(app_handle) = app_handle {
+        if let Err(e) = app_handle.emit_all(JOB_PROGRESS_EVENT, progress) {
+            tracing::error!("Error emitting job progress: {e}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
+};
+
+use flate2::read::GzDecoder;
+use once_cell::sync::Lazy;
+use tauri::State;
+use tracing::{level_filters::LevelFilter, Level};
+
+use crate::{
+    config::{get_or_create_config_dir, LOG_FILE},
+    error::AppError,
+    models::Range,
+    settings::{apply_settings, save_settings, LogLevel},
+    AppState,
+};
+
+use models::{LogParams, SerializedLogEntry, SerializedLogList};
+pub use redact::{redact, RedactingWriter, Redactor};
+use rotation::rotated_log_files;
+pub use rotation::{cleanup_rotated_logs, rotate_logs, COMPRESSED_SUFFIX};
+
+mod models;
+mod redact;
+mod rotation;
+
+const DEFAULT_LOG_LIMIT: u64 = 100;
+const MAX_LOG_LIMIT: u64 = 500;
+const MAX_MATCH_COUNTS: usize = 1000;
+
+// matching entries per rotated log file and filter - rotated files are not written to anymore,
+// so pages outside of them are served without reading (and decompressing) them again
+static MATCH_COUNTS: Lazy<Mutex<HashMap<MatchCountKey, u64>>> = Lazy::new(Default::default);
+
+#[derive(Hash, PartialEq, Eq)]
+struct MatchCountKey {
+    path: PathBuf,
+    // a file replaced by its compressed copy keeps the entries, but not the size
+    len: u64,
+    level: Option<String>,
+    search: Option<String>,
+}
+
+impl MatchCountKey {
+    fn get(&self) -> Option<u64> {
+        MATCH_COUNTS.lock().ok()?.get(self).copied()
+    }
+
+    fn insert(self, count: u64) {
+        if let Ok(mut counts) = MATCH_COUNTS.lock() {
+            if counts.len() >= MAX_MATCH_COUNTS {
+                counts.clear();
+            }
+            counts.insert(self, count);
+        }
+    }
+}
+
+/// Current log file first, rotated files from newest to oldest
+pub fn log_files(config_dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let rotated = rotated_log_files(config_dir)?;
+
+    let mut files = Vec::with_capacity(rotated.len() + 1);
+    let current = config_dir.join(LOG_FILE);
+    if current.exists() {
+        files.push(current);
+    }
+    files.extend(rotated);
+
+    Ok(files)
+}
+
+/// Parses a line in the JSON or compact text format (`<timestamp> <LEVEL> <target>: <message>`) -
+/// `None` for continuation lines of multi-line messages
+fn parse_line(line: &str, file: &str) -> Option<SerializedLogEntry> {
+    if line.starts_with('{') {
+        return parse_json_line(line, file);
+    }
+
+    let (timestamp, rest) = line.split_once(char::is_whitespace)?;
+
+    if !timestamp.starts_with(|c: char| c.is_ascii_digit()) || !timestamp.contains('T') {
+        return None;
+    }
+
+    // levels are padded to the same width
+    let (level, rest) = rest.trim_start().split_once(char::is_whitespace)?;
+    Level::from_str(level).ok()?;
+
+    let rest = rest.trim_start();
+    let (target, message) = match rest.split_once(": ") {
+        Some((target, message)) if !target.contains(char::is_whitespace) => (target, message),
+        _ => ("", rest),
+    };
+
+    Some(SerializedLogEntry {
+        timestamp: timestamp.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+        file: file.to_string(),
+    })
+}
+
+fn parse_json_line(line: &str, file: &str) -> Option<SerializedLogEntry> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let str_value = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    Some(SerializedLogEntry {
+        timestamp: value.get("timestamp")?.as_str()?.to_string(),
+        level: value.get("level")?.as_str()?.to_string(),
+        target: str_value(value.get("target")),
+        message: str_value(value.get("fields").and_then(|fields| fields.get("message"))),
+        file: file.to_string(),
+    })
+}
+
+/// Content of a log file - rotated files may be gzipped
+pub fn read_log_content(path: &Path) -> Result<String, AppError> {
+    let mut content = Vec::new();
+    let mut file = File::open(path)?;
+
+    if path.to_string_lossy().ends_with(COMPRESSED_SUFFIX) {
+        GzDecoder::new(file).read_to_end(&mut content)?;
+    } else {
+        file.read_to_end(&mut content)?;
+    }
+
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+fn read_log_file(path: &Path) -> Result<Vec<SerializedLogEntry>, AppError> {
+    let content = read_log_content(path)?;
+    let file = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut entries: Vec<SerializedLogEntry> = Vec::new();
+
+    for line in content.lines() {
+        match (parse_line(line, &file), entries.last_mut()) {
+            (Some(entry), _) => entries.push(entry),
+            (None, Some(last)) => {
+                last.message.push('\n');
+                last.message.push_str(line);
+            }
+            (None, None) => (),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn matches(entry: &SerializedLogEntry, level: Option<LevelFilter>, search: Option<&str>) -> bool {
+    if let Some(level) = level {
+        match Level::from_str(&entry.level) {
+            Ok(entry_level) if entry_level <= level => (),
+            _ => return false,
+        }
+    }
+
+    match search {
+        Some(search) => {
+            entry.message.to_lowercase().contains(search)
+                || entry.target.to_lowercase().contains(search)
+        }
+        None => true,
+    }
+}
+
+/// Reads entries of all log files (newest first) filtered by level and text - rotated files are
+/// only read if the requested page overlaps them or their number of matching entries is unknown
+fn read_logs_from(config_dir: &Path, params: &LogParams) -> Result<SerializedLogList, AppError> {
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_LOG_LIMIT).min(MAX_LOG_LIMIT);
+    let level = params.level.map(LevelFilter::from);
+    let search = params
+        .search
+        .as_ref()
+        .map(|search| search.trim().to_lowercase())
+        .filter(|search| !search.is_empty());
+
+    let current = config_dir.join(LOG_FILE);
+    let page_end = offset + limit;
+    let mut total = 0;
+    let mut items = Vec::new();
+
+    for path in log_files(config_dir)? {
+        // the current log file is still written to
+        let key = match std::fs::metadata(&path) {
+            Ok(metadata) if path != current => Some(MatchCountKey {
+                path: path.clone(),
+                len: metadata.len(),
+                level: level.map(|level| level.to_string()),
+                search: search.clone(),
+            }),
+            _ => None,
+        };
+
+        if let Some(count) = key.as_ref().and_then(MatchCountKey::get) {
+            if total + count <= offset || total >= page_end {
+                total += count;
+                continue;
+            }
+        }
+
+        let entries = read_log_file(&path).map_err(|e| e.logged("Error reading log file"))?;
+        let mut count = 0;
+
+        for entry in entries.into_iter().rev() {
+            if !matches(&entry, level, search.as_deref()) {
+                continue;
+            }
+
+            if total >= offset && (items.len() as u64) < limit {
+                items.push(entry);
+            }
+            total += 1;
+            count += 1;
+        }
+
+        if let Some(key) = key {
+            key.insert(count);
+        }
+    }
+
+    Ok(SerializedLogList {
+        range: Range {
+            offset,
+            limit,
+            total,
+        },
+        items,
+    })
+}
+
+#[tauri::command]
+pub async fn read_logs(params: LogParams) -> Result<SerializedLogList, AppError> {
+    let now = Instant::now();
+    let config_dir = get_or_create_config_dir();
+
+    let logs = tauri::async_runtime::spawn_blocking(move || read_logs_from(&config_dir, &params))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))??;
+
+    let elapsed = now.elapsed().as_millis();
+    tracing::debug!(
+        "Read {} of {} log entries in {elapsed} ms",
+        logs.items.len(),
+        logs.range.total
+    );
+    Ok(logs)
+}
+
+/// Switches the log level at runtime and saves it
+#[tauri::command]
+pub async fn set_log_level(level: LogLevel, state: State<'_, AppState>) -> Result<(), AppError> {
+    let config_dir = get_or_create_config_dir();
+    let previous = state.settings().await;
+    let mut settings = previous.clone();
+    settings.log.level = level;
+
+    save_settings(&config_dir, &settings)?;
+    apply_settings(&config_dir, &previous, &settings, &state).await;
+
+    Ok(())
+}
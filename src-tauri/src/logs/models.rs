@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{models::Range, settings::LogLevel};
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogParams {
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+    /// minimum severity - `warn` returns warnings and errors
+    pub level: Option<LogLevel>,
+    pub search: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedLogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub file: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedLogList {
+    pub range: Range,
+    pub items: Vec<SerializedLogEntry>,
+}
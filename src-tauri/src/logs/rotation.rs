@@ -0,0 +1,281 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{config::LOG_FILE, settings::LogSettings};
+
+pub const COMPRESSED_SUFFIX: &str = ".gz";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Rotated log files (`dcadmin.log.<timestamp>[.gz]`) from newest to oldest
+pub fn rotated_log_files(config_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(config_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| rotation_time(path).is_some())
+        .collect::<Vec<_>>();
+
+    files.sort_by_key(|path| std::cmp::Reverse(rotation_time(path)));
+
+    Ok(files)
+}
+
+fn rotation_time(path: &Path) -> Option<SystemTime> {
+    let name = path.file_name()?.to_str()?;
+    let suffix = name.strip_prefix(LOG_FILE)?.strip_prefix('.')?;
+    let timestamp = suffix.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(suffix);
+
+    timestamp
+        .parse::<u64>()
+        .ok()
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(COMPRESSED_SUFFIX)
+}
+
+/// Rotates the log file if it exceeds the configured size and applies the retention.
+/// Runs before logging is set up - problems are returned as warnings instead of failing the startup.
+pub fn rotate_logs(config_dir: &Path, settings: &LogSettings) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let log_file_path = config_dir.join(LOG_FILE);
+
+    let size = std::fs::metadata(&log_file_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    // hand-edited settings are not validated - never overflow
+    if size > settings.max_size_mb.saturating_mul(BYTES_PER_MB) {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0))
+            .as_secs();
+        let rotated_log_file_path = config_dir.join(format!("{LOG_FILE}.{timestamp}"));
+
+        if let Err(e) = std::fs::rename(&log_file_path, rotated_log_file_path) {
+            warnings.push(format!(
+                "Error rotating log file - appending to current file: {e}"
+            ));
+        }
+    }
+
+    // also picks up files rotated by previous versions or while compression was disabled
+    if settings.compress {
+        match rotated_log_files(config_dir) {
+            Ok(files) => {
+                for path in files.iter().filter(|path| !is_compressed(path)) {
+                    if let Err(e) = compress(path) {
+                        warnings.push(format!("Error compressing {}: {e}", path.display()));
+                    }
+                }
+            }
+            Err(e) => warnings.push(format!("Error listing rotated log files: {e}")),
+        }
+    }
+
+    warnings.extend(cleanup_rotated_logs(config_dir, settings));
+
+    warnings
+}
+
+/// Replaces the file with a gzipped copy - the original is kept if compression fails
+fn compress(path: &Path) -> std::io::Result<()> {
+    let compressed_path = PathBuf::from(format!("{}{COMPRESSED_SUFFIX}", path.display()));
+
+    let result = File::open(path).and_then(|file| {
+        let mut reader = BufReader::new(file);
+        let mut encoder = GzEncoder::new(File::create(&compressed_path)?, Compression::default());
+        std::io::copy(&mut reader, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => std::fs::remove_file(path),
+        Err(e) => {
+            let _ = std::fs::remove_file(&compressed_path);
+            Err(e)
+        }
+    }
+}
+
+/// Deletes rotated log files exceeding the configured count or age - returns warnings for files that could not be deleted
+pub fn cleanup_rotated_logs(config_dir: &Path, settings: &LogSettings) -> Vec<String> {
+    let files = match rotated_log_files(config_dir) {
+        Ok(files) => files,
+        Err(e) => return vec![format!("Error listing rotated log files: {e}")],
+    };
+
+    let max_age = Duration::from_secs(settings.max_age_days.saturating_mul(SECS_PER_DAY));
+    let now = SystemTime::now();
+
+    files
+        .iter()
+        .enumerate()
+        .filter(|(index, path)| {
+            let expired = settings.max_age_days > 0
+                && rotation_time(path)
+                    .and_then(|time| now.duration_since(time).ok())
+                    .map(|age| age > max_age)
+                    .unwrap_or(false);
+
+            *index >= settings.max_files || expired
+        })
+        .filter_map(|(_, path)| {
+            std::fs::remove_file(path)
+                .err()
+                .map(|e| format!("Error deleting {}: {e}", path.display()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, "2024-01-01T00:00:00Z  INFO app: test\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_rotation_time() {
+        assert_eq!(
+            rotation_time(Path::new("dcadmin.log.1700000000")),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            rotation_time(Path::new("dcadmin.log.1700000000.gz")),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(rotation_time(Path::new("dcadmin.log")), None);
+        assert_eq!(rotation_time(Path::new("dcadmin.log.old")), None);
+        assert_eq!(rotation_time(Path::new("other.log.1700000000")), None);
+    }
+
+    #[test]
+    fn test_rotated_log_files_newest_first() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        touch(dir, LOG_FILE);
+        touch(dir, "dcadmin.log.100");
+        touch(dir, "dcadmin.log.300.gz");
+        touch(dir, "dcadmin.log.200");
+        touch(dir, "settings.toml");
+
+        let files = rotated_log_files(dir).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["dcadmin.log.300.gz", "dcadmin.log.200", "dcadmin.log.100"]
+        );
+    }
+
+    #[test]
+    fn test_cleanup_keeps_max_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let now = now_secs();
+        for age in 1..=4 {
+            touch(dir, &format!("{LOG_FILE}.{}", now - age));
+        }
+
+        let settings = LogSettings {
+            max_files: 2,
+            max_age_days: 0,
+            ..LogSettings::default()
+        };
+
+        assert!(cleanup_rotated_logs(dir, &settings).is_empty());
+
+        let files = rotated_log_files(dir).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                dir.join(format!("{LOG_FILE}.{}", now - 1)),
+                dir.join(format!("{LOG_FILE}.{}", now - 2))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cleanup_deletes_expired_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let now = now_secs();
+        let recent = touch(dir, &format!("{LOG_FILE}.{}", now - SECS_PER_DAY));
+        touch(dir, &format!("{LOG_FILE}.{}.gz", now - 3 * SECS_PER_DAY));
+
+        let settings = LogSettings {
+            max_age_days: 2,
+            ..LogSettings::default()
+        };
+
+        assert!(cleanup_rotated_logs(dir, &settings).is_empty());
+        assert_eq!(rotated_log_files(dir).unwrap(), vec![recent]);
+    }
+
+    #[test]
+    fn test_cleanup_with_huge_max_age() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let rotated = touch(dir, &format!("{LOG_FILE}.1"));
+
+        let settings = LogSettings {
+            max_age_days: u64::MAX,
+            ..LogSettings::default()
+        };
+
+        assert!(cleanup_rotated_logs(dir, &settings).is_empty());
+        assert_eq!(rotated_log_files(dir).unwrap(), vec![rotated]);
+    }
+
+    #[test]
+    fn test_rotate_compresses_large_log_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join(LOG_FILE), vec![b'a'; BYTES_PER_MB as usize + 1]).unwrap();
+
+        let settings = LogSettings {
+            max_size_mb: 1,
+            ..LogSettings::default()
+        };
+
+        assert!(rotate_logs(dir, &settings).is_empty());
+        assert!(!dir.join(LOG_FILE).exists());
+
+        let files = rotated_log_files(dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(is_compressed(&files[0]));
+    }
+
+    #[test]
+    fn test_rotate_keeps_small_log_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        touch(dir, LOG_FILE);
+
+        assert!(rotate_logs(dir, &LogSettings::default()).is_empty());
+        assert!(dir.join(LOG_FILE).exists());
+        assert!(rotated_log_files(dir).unwrap().is_empty());
+    }
+}
@@ -3,27 +3,73 @@
 
 use config::setup_logging;
 use models::AppState;
+use tauri::Manager;
 pub use models::{ROLE_CONFIG_MANAGER, ROLE_ROOM_MANAGER, ROLE_USER_MANAGER, ROLE_GROUP_MANAGER, ROLE_AUDITOR, ROLE_GUEST_USER};
 
 mod auth;
+mod cache;
 mod config;
 mod customer;
+mod diagnostics;
+mod error;
 mod events;
+mod export;
+mod filter;
 mod groups;
+mod guard;
+mod jobs;
+mod logs;
 mod models;
+mod network;
+mod pagination;
 mod permissions;
+mod profiles;
+mod settings;
+mod snapshots;
 pub (crate) mod users;
 
 fn main() {
     let config_dir = config::get_or_create_config_dir();
-    setup_logging(&config_dir, false);
+    // settings are loaded before logging is set up - errors are logged afterwards
+    let loaded_settings = settings::load_settings(&config_dir);
+    let app_settings = loaded_settings.clone().unwrap_or_default();
+    let credentials = auth::CredentialManager::new(app_settings.credential_backend);
+    let log_handle = setup_logging(&config_dir, &app_settings.log);
+
+    if let Err(e) = loaded_settings {
+        tracing::error!("Error loading settings - using defaults: {e}");
+    }
+
+    if !credentials.keyring_available() {
+        tracing::warn!("No OS keyring available - using encrypted file credential store");
+    }
+
+    let profiles = profiles::load_profiles(&config_dir);
+    let jobs = jobs::JobManager::load(&config_dir, app_settings.export.max_running_jobs);
 
     tauri::Builder::default()
-        .manage(AppState::default())
+        .manage(AppState::new(
+            profiles,
+            credentials,
+            app_settings,
+            Some(log_handle),
+            snapshots::SnapshotStore::new(&config_dir),
+            jobs,
+        ))
+        .setup(|app| {
+            app.state::<AppState>().set_app_handle(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             auth::init_auth_code_flow,
             auth::connect,
+            auth::logout,
+            auth::get_credential_store,
+            auth::set_credential_backend,
+            auth::unlock_credential_store,
             auth::validate_dracoon_url,
+            cache::get_cache_stats,
+            cache::invalidate_cache,
             customer::get_customer_info,
             events::get_events,
             events::get_operation_types,
@@ -39,6 +85,31 @@ fn main() {
             groups::get_group_users,
             groups::export_group_users,
             groups::export_all_group_users,
+            jobs::list_jobs,
+            jobs::get_job,
+            jobs::cancel_job,
+            jobs::resume_job,
+            profiles::get_profiles,
+            profiles::add_profile,
+            profiles::switch_profile,
+            profiles::remove_profile,
+            profiles::get_oauth_client,
+            profiles::set_oauth_client,
+            network::get_network_settings,
+            network::update_network_settings,
+            settings::get_settings,
+            settings::update_settings,
+            logs::read_logs,
+            logs::set_log_level,
+            diagnostics::export_diagnostics,
+            snapshots::take_snapshot,
+            snapshots::list_snapshots,
+            snapshots::delete_snapshot,
+            snapshots::get_snapshot_users,
+            snapshots::get_snapshot_groups,
+            snapshots::get_snapshot_group_users,
+            snapshots::get_snapshot_permissions,
+            snapshots::get_snapshot_events,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
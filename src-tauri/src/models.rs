@@ -1,21 +1,45 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
+use chrono::Utc;
 use dco3::{
     auth::{Connected, Disconnected},
     public::SoftwareVersionData,
     user::UserAccount,
-    Dracoon, FilterOperator, FilterQueryBuilder, ListAllParams, OAuth2Flow,
+    Dracoon, DracoonClientError, FilterOperator, FilterQueryBuilder, ListAllParams, OAuth2Flow,
 };
-use keyring::Entry;
-use moka::future::Cache;
 use serde::{Deserialize, Serialize};
-use tauri::async_runtime::RwLock;
+use tauri::{
+    async_runtime::{JoinHandle, RwLock},
+    AppHandle, Manager,
+};
 
 use crate::{
-    config::{log_dracoon_error, setup_cache},
+    auth::{build_client, exchange_auth_code, CredentialManager, Credentials, PkceChallenge},
+    cache::{CacheName, TrackedCache},
+    config::{get_client_credentials, get_or_create_config_dir, LogHandle, OAuthClientConfig},
     customer::SerializedCustomerInfo,
+    error::AppError,
     events::{EventsCacheKey, SerializedEventList, SerializedOperationTypes},
+    filter::{self, parse_filter},
+    groups::{
+        GroupCacheKey, GroupUsersCacheKey, GroupsCacheKey, SerializedGroupInfo,
+        SerializedGroupList, SerializedGroupUserList,
+    },
+    jobs::JobManager,
+    network::{with_timeout, NetworkSettings, RetryPolicy},
+    settings::{AppSettings, CacheSettings, LogLevel, RedactionSettings},
     permissions::{PermissionsCacheKey, SerializedNodePermissionsList},
+    profiles::{StoredOAuthClient, StoredProfile},
+    snapshots::SnapshotStore,
+    users::{SerializedUserList, UsersCacheKey},
 };
 
 pub const ROLE_ROOM_MANAGER: &str = "ROOM_MANAGER";
@@ -24,63 +48,189 @@ pub const ROLE_AUDITOR: &str = "LOG_AUDITOR";
 pub const ROLE_USER_MANAGER: &str = "USER_MANAGER";
 pub const ROLE_GROUP_MANAGER: &str = "GROUP_MANAGER";
 pub const ROLE_GUEST_USER: &str = "GUEST_USER";
-const DEFAULT_MAX_CACHE_ENTITY_COUNT: u64 = 100;
-const DEFAULT_MAX_CACHE_STATIC_COUNT: u64 = 1;
+// static data is cached once per profile
+const MAX_CACHE_STATIC_COUNT: u64 = 1;
+// command timings kept for diagnostics
+const MAX_COMMAND_TIMINGS: usize = 200;
+pub const SESSION_EXPIRED_EVENT: &str = "session-expired";
 
 pub enum AppAuth {
     Unset,
-    AuthCode,
+    AuthCode {
+        pkce: PkceChallenge,
+        redirect_uri: String,
+    },
     RefreshToken(String),
 }
 
 pub struct AppState {
+    profiles: Arc<RwLock<HashMap<String, Arc<Profile>>>>,
+    active_profile: Arc<RwLock<Option<String>>>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    credentials: Arc<CredentialManager>,
+    settings: Arc<RwLock<AppSettings>>,
+    log_handle: Option<LogHandle>,
+    timings: Arc<Mutex<VecDeque<CommandTiming>>>,
+    snapshots: Arc<SnapshotStore>,
+    jobs: JobManager,
+}
+
+/// A named connection to a DRACOON instance with its own client, credentials and caches
+pub struct Profile {
+    name: String,
+    url: String,
+    oauth_client: RwLock<Option<StoredOAuthClient>>,
+    // secret of the custom OAuth client - loaded from the credential store on login
+    client_secret: RwLock<Option<String>>,
     client: Arc<RwLock<WrappedClient>>,
     app_auth: Arc<RwLock<AppAuth>>,
-    entry: Arc<RwLock<Option<Entry>>>,
-    cache: AppCache,
+    entry: Arc<RwLock<Option<Credentials>>>,
+    // roles of the connected user - cached on connect for command guards
+    roles: RwLock<Option<Vec<String>>>,
+    cache: RwLock<AppCache>,
+    // incremented on every reconnect to detect concurrent session recoveries
+    session: AtomicU64,
+    recovery: tokio::sync::Mutex<()>,
+    // loopback listener waiting for the redirect of the current auth code flow
+    callback_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Client of a profile with timeout and retry policy resolved once
+pub struct ProfileClient<'a> {
+    state: &'a AppState,
+    profile: &'a Profile,
+    // client and session counter - replaced after a session recovery
+    session: RwLock<(Dracoon<Connected>, u64)>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionExpired {
+    pub profile: String,
+    pub url: String,
 }
 
 pub struct AppCache {
-    pub permissions: Cache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>>,
-    pub customer: Cache<String, Arc<SerializedCustomerInfo>>,
-    pub events: Cache<EventsCacheKey, Arc<SerializedEventList>>,
-    pub operations: Cache<String, Arc<SerializedOperationTypes>>,
+    pub permissions: TrackedCache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>>,
+    pub customer: TrackedCache<String, Arc<SerializedCustomerInfo>>,
+    pub events: TrackedCache<EventsCacheKey, Arc<SerializedEventList>>,
+    pub operations: TrackedCache<String, Arc<SerializedOperationTypes>>,
+    pub users: TrackedCache<UsersCacheKey, Arc<SerializedUserList>>,
+    pub groups: TrackedCache<GroupsCacheKey, Arc<SerializedGroupList>>,
+    pub group: TrackedCache<GroupCacheKey, Arc<SerializedGroupInfo>>,
+    pub group_users: TrackedCache<GroupUsersCacheKey, Arc<SerializedGroupUserList>>,
 }
 
 impl AppCache {
-    pub fn new() -> Self {
+    pub fn new(settings: &CacheSettings) -> Self {
+        let ttl = Duration::from_secs(settings.ttl_secs);
+        let events_ttl = Duration::from_secs(settings.events_ttl_secs);
+        let static_ttl = Duration::from_secs(settings.static_ttl_secs);
+
         AppCache {
-            permissions: setup_cache(DEFAULT_MAX_CACHE_ENTITY_COUNT, None),
-            customer: setup_cache(
-                DEFAULT_MAX_CACHE_STATIC_COUNT,
-                Some(Duration::from_secs(30 * 60)),
-            ),
-            events: setup_cache(
-                DEFAULT_MAX_CACHE_ENTITY_COUNT,
-                Some(Duration::from_secs(60)),
-            ),
-            operations: setup_cache(
-                DEFAULT_MAX_CACHE_STATIC_COUNT,
-                Some(Duration::from_secs(30 * 60)),
-            ),
+            permissions: TrackedCache::new("permissions", settings.max_entries, ttl),
+            customer: TrackedCache::new("customer", MAX_CACHE_STATIC_COUNT, static_ttl),
+            events: TrackedCache::new("events", settings.max_entries, events_ttl),
+            operations: TrackedCache::new("operations", MAX_CACHE_STATIC_COUNT, static_ttl),
+            users: TrackedCache::new("users", settings.max_entries, ttl),
+            groups: TrackedCache::new("groups", settings.max_entries, ttl),
+            group: TrackedCache::new("group", settings.max_entries, ttl),
+            group_users: TrackedCache::new("groupUsers", settings.max_entries, ttl),
         }
     }
 
-    pub fn permissions(&self) -> &Cache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>> {
+    pub fn permissions(
+        &self,
+    ) -> &TrackedCache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>> {
         &self.permissions
     }
 
-    pub fn customer(&self) -> &Cache<String, Arc<SerializedCustomerInfo>> {
+    pub fn customer(&self) -> &TrackedCache<String, Arc<SerializedCustomerInfo>> {
         &self.customer
     }
 
-    pub fn events(&self) -> &Cache<EventsCacheKey, Arc<SerializedEventList>> {
+    pub fn events(&self) -> &TrackedCache<EventsCacheKey, Arc<SerializedEventList>> {
         &self.events
     }
 
-    pub fn operations(&self) -> &Cache<String, Arc<SerializedOperationTypes>> {
+    pub fn operations(&self) -> &TrackedCache<String, Arc<SerializedOperationTypes>> {
         &self.operations
     }
+
+    pub fn users(&self) -> &TrackedCache<UsersCacheKey, Arc<SerializedUserList>> {
+        &self.users
+    }
+
+    pub fn groups(&self) -> &TrackedCache<GroupsCacheKey, Arc<SerializedGroupList>> {
+        &self.groups
+    }
+
+    pub fn group(&self) -> &TrackedCache<GroupCacheKey, Arc<SerializedGroupInfo>> {
+        &self.group
+    }
+
+    pub fn group_users(&self) -> &TrackedCache<GroupUsersCacheKey, Arc<SerializedGroupUserList>> {
+        &self.group_users
+    }
+
+    /// Entries, hit rate and age of the oldest entry per cache
+    pub async fn stats(&self) -> Vec<SerializedCacheStats> {
+        vec![
+            self.permissions.stats().await,
+            self.customer.stats().await,
+            self.events.stats().await,
+            self.operations.stats().await,
+            self.users.stats().await,
+            self.groups.stats().await,
+            self.group.stats().await,
+            self.group_users.stats().await,
+        ]
+    }
+
+    pub fn invalidate_all(&self) {
+        self.permissions.invalidate_all();
+        self.customer.invalidate_all();
+        self.events.invalidate_all();
+        self.operations.invalidate_all();
+        self.users.invalidate_all();
+        self.groups.invalidate_all();
+        self.group.invalidate_all();
+        self.group_users.invalidate_all();
+    }
+
+    /// Drops entries of one or all caches, optionally only for a DRACOON instance
+    pub async fn invalidate(&self, cache: Option<CacheName>, url: Option<&str>) -> u64 {
+        let mut invalidated = 0;
+
+        if matches!(cache, None | Some(CacheName::Permissions)) {
+            invalidated += self.permissions.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Customer)) {
+            invalidated += self.customer.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Events)) {
+            invalidated += self.events.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Operations)) {
+            invalidated += self.operations.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Users)) {
+            invalidated += self.users.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Groups)) {
+            invalidated += self.groups.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::Group)) {
+            invalidated += self.group.invalidate(url).await;
+        }
+        if matches!(cache, None | Some(CacheName::GroupUsers)) {
+            invalidated += self.group_users.invalidate(url).await;
+        }
+
+        invalidated
+    }
 }
 
 pub enum WrappedClient {
@@ -91,16 +241,133 @@ pub enum WrappedClient {
 
 impl Default for AppState {
     fn default() -> Self {
+        let settings = AppSettings::default();
+        let config_dir = get_or_create_config_dir();
+        let jobs = JobManager::load(&config_dir, settings.export.max_running_jobs);
+
+        AppState::new(
+            Vec::new(),
+            CredentialManager::new(Default::default()),
+            settings,
+            None,
+            SnapshotStore::new(&config_dir),
+            jobs,
+        )
+    }
+}
+
+impl AppState {
+    pub fn new(
+        profiles: Vec<StoredProfile>,
+        credentials: CredentialManager,
+        settings: AppSettings,
+        log_handle: Option<LogHandle>,
+        snapshots: SnapshotStore,
+        jobs: JobManager,
+    ) -> Self {
+        let profiles = profiles
+            .into_iter()
+            .map(|profile| {
+                let name = profile.name.clone();
+                (name, Arc::new(Profile::from_stored(profile, &settings.cache)))
+            })
+            .collect();
+
         AppState {
-            client: Arc::new(RwLock::new(WrappedClient::Unset)),
-            app_auth: Arc::new(RwLock::new(AppAuth::Unset)),
-            entry: Arc::new(RwLock::new(None)),
-            cache: AppCache::new(),
+            profiles: Arc::new(RwLock::new(profiles)),
+            active_profile: Arc::new(RwLock::new(None)),
+            app_handle: Arc::new(Mutex::new(None)),
+            credentials: Arc::new(credentials),
+            settings: Arc::new(RwLock::new(settings)),
+            log_handle,
+            timings: Arc::new(Mutex::new(VecDeque::with_capacity(MAX_COMMAND_TIMINGS))),
+            snapshots: Arc::new(snapshots),
+            jobs,
+        }
+    }
+
+    pub fn credentials(&self) -> &CredentialManager {
+        &self.credentials
+    }
+
+    pub fn snapshots(&self) -> Arc<SnapshotStore> {
+        self.snapshots.clone()
+    }
+
+    pub fn jobs(&self) -> JobManager {
+        self.jobs.clone()
+    }
+
+    pub async fn settings(&self) -> AppSettings {
+        self.settings.read().await.clone()
+    }
+
+    pub async fn set_settings(&self, settings: AppSettings) {
+        *self.settings.write().await = settings;
+    }
+
+    pub async fn network_settings(&self) -> NetworkSettings {
+        self.settings.read().await.network.clone()
+    }
+
+    /// Starts timing a command for diagnostics
+    pub fn timer(&self, command: &'static str) -> CommandTimer<'_> {
+        CommandTimer {
+            state: self,
+            command,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Keeps the duration of a command for diagnostics - the oldest timings are dropped
+    pub fn record_timing(&self, command: &str, elapsed_ms: u128, failed: bool) {
+        let mut timings = self.timings.lock().expect("timings lock poisoned");
+
+        if timings.len() >= MAX_COMMAND_TIMINGS {
+            timings.pop_front();
+        }
+
+        timings.push_back(CommandTiming {
+            command: command.to_string(),
+            elapsed_ms: elapsed_ms as u64,
+            timestamp: Utc::now().to_rfc3339(),
+            failed,
+        });
+    }
+
+    pub fn timings(&self) -> Vec<CommandTiming> {
+        self.timings
+            .lock()
+            .expect("timings lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Switches the log level without restart
+    pub fn set_log_level(&self, level: LogLevel) {
+        if let Some(log_handle) = &self.log_handle {
+            log_handle.set_level(level);
+        }
+    }
+
+    /// Applies changed redaction rules to the log output without restart
+    pub fn set_log_redaction(&self, settings: &RedactionSettings) {
+        if let Some(log_handle) = &self.log_handle {
+            log_handle.set_redaction(settings);
+        }
+    }
+
+    /// Replaces the caches of all profiles - cached entries are dropped
+    pub async fn rebuild_caches(&self, settings: &CacheSettings) {
+        for profile in self.profiles().await {
+            profile.set_cache(AppCache::new(settings)).await;
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedUserAccount {
     pub first_name: String,
@@ -160,14 +427,397 @@ impl From<(UserAccount, SoftwareVersionData)> for SerializedUserAccount {
 }
 
 impl AppState {
-    pub fn entry(&self) -> &Arc<RwLock<Option<Entry>>> {
+    pub async fn profiles(&self) -> Vec<Arc<Profile>> {
+        let mut profiles = self
+            .profiles
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        profiles.sort_by(|a, b| a.name().cmp(b.name()));
+        profiles
+    }
+
+    pub async fn stored_profiles(&self) -> Vec<StoredProfile> {
+        let mut stored_profiles = Vec::new();
+
+        for profile in self.profiles().await {
+            stored_profiles.push(profile.to_stored().await);
+        }
+
+        stored_profiles
+    }
+
+    pub async fn get_profile(&self, name: &str) -> Result<Arc<Profile>, AppError> {
+        self.profiles
+            .read()
+            .await
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidInput(format!("Profile {name} not found")))
+    }
+
+    /// Returns the profile with the given name or creates it - an existing profile with a different url
+    /// is not replaced, as its session and refresh token would be left behind
+    pub async fn get_or_create_profile(
+        &self,
+        name: &str,
+        url: &str,
+    ) -> Result<Arc<Profile>, AppError> {
+        let mut profiles = self.profiles.write().await;
+
+        match profiles.get(name) {
+            Some(profile) if profile.url() == url => Ok(profile.clone()),
+            Some(_) => {
+                tracing::error!("Profile {name} already exists with a different url");
+                Err(AppError::InvalidInput(format!(
+                    "Profile {name} already exists with a different url"
+                )))
+            }
+            None => {
+                let cache_settings = self.settings.read().await.cache.clone();
+                let profile = Arc::new(Profile::new(
+                    name.to_string(),
+                    url.to_string(),
+                    &cache_settings,
+                ));
+                profiles.insert(name.to_string(), profile.clone());
+                Ok(profile)
+            }
+        }
+    }
+
+    pub async fn remove_profile(&self, name: &str) -> Result<Arc<Profile>, AppError> {
+        let profile = self
+            .profiles
+            .write()
+            .await
+            .remove(name)
+            .ok_or_else(|| AppError::InvalidInput(format!("Profile {name} not found")))?;
+
+        let mut active_profile = self.active_profile.write().await;
+        if active_profile.as_deref() == Some(name) {
+            *active_profile = None;
+        }
+
+        Ok(profile)
+    }
+
+    pub async fn active_profile_name(&self) -> Option<String> {
+        self.active_profile.read().await.clone()
+    }
+
+    pub async fn active_profile(&self) -> Result<Arc<Profile>, AppError> {
+        let name = self
+            .active_profile_name()
+            .await
+            .ok_or(AppError::NotConnected)?;
+
+        self.get_profile(&name).await
+    }
+
+    pub async fn set_active_profile(&self, name: &str) -> Result<Arc<Profile>, AppError> {
+        let profile = self.get_profile(name).await?;
+        *self.active_profile.write().await = Some(name.to_string());
+        Ok(profile)
+    }
+
+    pub async fn get_client(&self) -> Result<Dracoon<Connected>, AppError> {
+        self.active_profile().await?.get_client().await
+    }
+
+    pub fn app_handle(&self) -> Option<AppHandle> {
+        self.app_handle.lock().ok().and_then(|handle| handle.clone())
+    }
+
+    pub fn set_app_handle(&self, app_handle: AppHandle) {
+        if let Ok(mut handle) = self.app_handle.lock() {
+            *handle = Some(app_handle);
+        }
+    }
+
+    /// Runs a request with the client of the active profile - see `ProfileClient::call`
+    pub async fn call<T, F, Fut>(&self, msg: &str, request: F) -> Result<T, AppError>
+    where
+        F: Fn(Dracoon<Connected>) -> Fut,
+        Fut: Future<Output = Result<T, DracoonClientError>>,
+    {
+        let profile = self.active_profile().await?;
+        self.call_with(&profile, msg, request).await
+    }
+
+    /// Runs a request with the client of a profile - background jobs keep the profile they were queued with
+    pub async fn call_with<T, F, Fut>(
+        &self,
+        profile: &Profile,
+        msg: &str,
+        request: F,
+    ) -> Result<T, AppError>
+    where
+        F: Fn(Dracoon<Connected>) -> Fut,
+        Fut: Future<Output = Result<T, DracoonClientError>>,
+    {
+        self.profile_client(profile).await?.call(msg, request).await
+    }
+
+    /// Resolves the client and network settings of a profile once - for jobs sending many requests
+    pub async fn profile_client<'a>(
+        &'a self,
+        profile: &'a Profile,
+    ) -> Result<ProfileClient<'a>, AppError> {
+        let session = profile.get_session().await?;
+        let network = self.network_settings().await;
+
+        Ok(ProfileClient {
+            state: self,
+            profile,
+            session: RwLock::new(session),
+            timeout: network.timeout(),
+            retry: network.retry_policy(),
+        })
+    }
+
+    fn emit_session_expired(&self, profile: &Profile) {
+        let payload = SessionExpired {
+            profile: profile.name().to_string(),
+            url: profile.url().to_string(),
+        };
+
+        match self.app_handle.lock().as_deref() {
+            Ok(Some(app_handle)) => {
+                if let Err(e) = app_handle.emit_all(SESSION_EXPIRED_EVENT, payload) {
+                    tracing::error!("Error emitting session expired event: {e}");
+                }
+            }
+            _ => tracing::warn!("No app handle to emit session expired event"),
+        }
+    }
+
+    pub async fn get_permissions_cache(
+        &self,
+    ) -> Result<TrackedCache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>>, AppError>
+    {
+        Ok(self.active_profile().await?.get_permissions_cache().await)
+    }
+
+    pub async fn get_customer_cache(
+        &self,
+    ) -> Result<TrackedCache<String, Arc<SerializedCustomerInfo>>, AppError> {
+        Ok(self.active_profile().await?.get_customer_cache().await)
+    }
+
+    pub async fn get_events_cache(
+        &self,
+    ) -> Result<TrackedCache<EventsCacheKey, Arc<SerializedEventList>>, AppError> {
+        Ok(self.active_profile().await?.get_events_cache().await)
+    }
+
+    pub async fn get_operations_cache(
+        &self,
+    ) -> Result<TrackedCache<String, Arc<SerializedOperationTypes>>, AppError> {
+        Ok(self.active_profile().await?.get_operations_cache().await)
+    }
+
+    pub async fn get_group_cache(
+        &self,
+    ) -> Result<TrackedCache<GroupCacheKey, Arc<SerializedGroupInfo>>, AppError> {
+        Ok(self.active_profile().await?.get_group_cache().await)
+    }
+}
+
+impl ProfileClient<'_> {
+    /// Runs a request with the client of the profile.
+    /// dco3 already retries rate limits, 5xx and connection errors with its own backoff - only timeouts
+    /// (which cancel the request including these retries) are retried here with the retry policy.
+    /// On authentication failures the session is recovered via refresh token and the request is retried once.
+    /// If the session cannot be recovered, the UI is notified to ask for a new login.
+    pub async fn call<T, F, Fut>(&self, msg: &str, request: F) -> Result<T, AppError>
+    where
+        F: Fn(Dracoon<Connected>) -> Fut,
+        Fut: Future<Output = Result<T, DracoonClientError>>,
+    {
+        let (client, session) = self.session.read().await.clone();
+
+        let result = self
+            .retry
+            .run_if(msg, AppError::is_timeout, || {
+                with_timeout(self.timeout, request(client.clone()))
+            })
+            .await;
+
+        let e = match result {
+            Ok(result) => return Ok(result),
+            Err(e) if e.is_auth_error() => e,
+            Err(e) => return Err(e.logged(msg)),
+        };
+
+        tracing::warn!("Authentication failed - recovering session: {e}");
+
+        if let Err(recovery_error) = self.profile.recover_session(session).await {
+            tracing::error!("Error recovering session: {recovery_error}");
+            self.state.emit_session_expired(self.profile);
+            return Err(e.logged(msg));
+        }
+
+        let (client, session) = self.profile.get_session().await?;
+        *self.session.write().await = (client.clone(), session);
+
+        self.retry
+            .run_if(msg, AppError::is_timeout, || {
+                with_timeout(self.timeout, request(client.clone()))
+            })
+            .await
+            .map_err(|e| {
+                let e = e.logged(msg);
+                if e.is_auth_error() {
+                    self.state.emit_session_expired(self.profile);
+                }
+                e
+            })
+    }
+}
+
+impl Profile {
+    pub fn new(name: String, url: String, cache: &CacheSettings) -> Self {
+        Profile {
+            name,
+            url,
+            oauth_client: RwLock::new(None),
+            client_secret: RwLock::new(None),
+            client: Arc::new(RwLock::new(WrappedClient::Unset)),
+            app_auth: Arc::new(RwLock::new(AppAuth::Unset)),
+            entry: Arc::new(RwLock::new(None)),
+            roles: RwLock::new(None),
+            cache: RwLock::new(AppCache::new(cache)),
+            session: AtomicU64::new(0),
+            recovery: tokio::sync::Mutex::new(()),
+            callback_task: Mutex::new(None),
+        }
+    }
+
+    pub fn from_stored(stored: StoredProfile, cache: &CacheSettings) -> Self {
+        let profile = Profile::new(stored.name, stored.url, cache);
+        Profile {
+            oauth_client: RwLock::new(stored.oauth_client),
+            ..profile
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn to_stored(&self) -> StoredProfile {
+        StoredProfile {
+            name: self.name.clone(),
+            url: self.url.clone(),
+            oauth_client: self.oauth_client.read().await.clone(),
+        }
+    }
+
+    /// OAuth client configured for this profile or the bundled default
+    pub async fn oauth_client(&self) -> Result<OAuthClientConfig, AppError> {
+        match &*self.oauth_client.read().await {
+            Some(oauth_client) => {
+                let client_secret = self.client_secret.read().await.clone().ok_or_else(|| {
+                    tracing::error!("OAuth client secret of profile {} not loaded", self.name);
+                    AppError::Credentials("No stored OAuth client secret".to_string())
+                })?;
+
+                Ok(OAuthClientConfig {
+                    client_id: oauth_client.client_id.clone(),
+                    client_secret,
+                    redirect_uri: oauth_client.redirect_uri.clone(),
+                })
+            }
+            None => get_client_credentials(),
+        }
+    }
+
+    pub async fn custom_oauth_client(&self) -> Option<StoredOAuthClient> {
+        self.oauth_client.read().await.clone()
+    }
+
+    /// Sets the OAuth client - the secret has to be stored in the credential store by the caller
+    pub async fn set_oauth_client(&self, oauth_client: Option<OAuthClientConfig>) {
+        let (oauth_client, client_secret) = match oauth_client {
+            Some(oauth_client) => (
+                Some(StoredOAuthClient {
+                    client_id: oauth_client.client_id,
+                    redirect_uri: oauth_client.redirect_uri,
+                }),
+                Some(oauth_client.client_secret),
+            ),
+            None => (None, None),
+        };
+
+        *self.oauth_client.write().await = oauth_client;
+        *self.client_secret.write().await = client_secret;
+    }
+
+    pub async fn has_client_secret(&self) -> bool {
+        self.client_secret.read().await.is_some()
+    }
+
+    pub async fn set_client_secret(&self, client_secret: String) {
+        *self.client_secret.write().await = Some(client_secret);
+    }
+
+    pub async fn is_connected(&self) -> bool {
+        matches!(&*self.client.read().await, WrappedClient::Connected(_))
+    }
+
+    pub fn entry(&self) -> &Arc<RwLock<Option<Credentials>>> {
         &self.entry
     }
 
-    pub async fn set_entry(&self, entry: Entry) {
+    pub async fn set_entry(&self, entry: Credentials) {
         *self.entry.write().await = Some(entry);
     }
 
+    /// Removes the stored refresh token of the current session (if any)
+    pub async fn take_entry(&self) -> Option<Credentials> {
+        self.entry.write().await.take()
+    }
+
+    /// Revokes the tokens (if connected) and resets client, auth and caches
+    pub async fn logout(&self) -> Result<(), AppError> {
+        let client = std::mem::replace(&mut *self.client.write().await, WrappedClient::Unset);
+        *self.app_auth.write().await = AppAuth::Unset;
+        *self.roles.write().await = None;
+        self.cache.read().await.invalidate_all();
+
+        if let WrappedClient::Connected(client) = client {
+            client
+                .logout()
+                .await
+                .map_err(|e| AppError::from(e).logged("Error revoking tokens"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Roles of the connected user - `None` if not connected yet
+    pub async fn roles(&self) -> Option<Vec<String>> {
+        self.roles.read().await.clone()
+    }
+
+    pub async fn set_roles(&self, account: &UserAccount) {
+        let roles = account
+            .user_roles
+            .items
+            .iter()
+            .map(|role| role.name.clone())
+            .collect();
+
+        *self.roles.write().await = Some(roles);
+    }
+
     pub fn app_auth(&self) -> Arc<RwLock<AppAuth>> {
         self.app_auth.clone()
     }
@@ -176,44 +826,88 @@ impl AppState {
         *self.app_auth.write().await = AppAuth::RefreshToken(refresh_token);
     }
 
-    pub async fn set_auth_code(&self) {
-        *self.app_auth.write().await = AppAuth::AuthCode;
+    pub async fn set_auth_code(&self, pkce: PkceChallenge, redirect_uri: String) {
+        *self.app_auth.write().await = AppAuth::AuthCode { pkce, redirect_uri };
+    }
+
+    /// Keeps the loopback listener of a new auth code flow - a listener of a previous flow is stopped
+    pub fn set_callback_task(&self, task: JoinHandle<()>) {
+        let previous = self
+            .callback_task
+            .lock()
+            .expect("callback task lock poisoned")
+            .replace(task);
+
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+    }
+
+    /// Stops waiting for the redirect, e.g. after connecting with a manually entered auth code
+    pub fn abort_callback_task(&self) {
+        let task = self
+            .callback_task
+            .lock()
+            .expect("callback task lock poisoned")
+            .take();
+
+        if let Some(task) = task {
+            tracing::debug!("Stopping loopback listener of profile {}", self.name);
+            task.abort();
+        }
     }
 
     pub async fn init_client(&self, client: Dracoon<Disconnected>) {
         *self.client.write().await = WrappedClient::Disconnected(client);
     }
 
-    pub async fn connect(&self, auth_code: String) -> Result<(UserAccount, String), String> {
+    pub async fn connect(
+        &self,
+        auth_code: String,
+        pkce: &PkceChallenge,
+        redirect_uri: &str,
+        network: &NetworkSettings,
+    ) -> Result<(UserAccount, String), AppError> {
         let read_lock = self.client.read().await;
 
         let client_clone = match &*read_lock {
             WrappedClient::Disconnected(client) => client.clone(),
             WrappedClient::Connected(client) => {
                 return Ok((
-                    client.get_user_info().await.map_err(|e| {
-                        log_dracoon_error(&e, Some("Error fetching user info"));
-                        e.to_string()
-                    })?,
+                    client
+                        .get_user_info()
+                        .await
+                        .map_err(|e| AppError::from(e).logged("Error fetching user info"))?,
                     client.get_refresh_token().await.to_string(),
                 ))
             }
-            WrappedClient::Unset => return Err("Client not initialized".to_string()),
+            WrappedClient::Unset => return Err(AppError::NotConnected),
         };
         // release read lock
         drop(read_lock);
 
+        // dco3 does not send a code verifier - exchange the code here and connect via refresh token
+        let oauth_client = self.oauth_client().await?;
+        let refresh_token = exchange_auth_code(
+            network,
+            &client_clone.get_base_url().to_string(),
+            &oauth_client.client_id,
+            &oauth_client.client_secret,
+            &auth_code,
+            redirect_uri,
+            pkce,
+        )
+        .await?;
+
         let client = client_clone
-            .connect(OAuth2Flow::authorization_code(auth_code))
+            .connect(OAuth2Flow::refresh_token(refresh_token))
             .await
-            .map_err(|e| {
-                tracing::error!("Error connecting with auth code: {e}");
-                e.to_string()
-            })?;
+            .map_err(|e| AppError::from(e).logged("Error connecting with auth code"))?;
 
         let refresh_token = client.get_refresh_token().await.to_string();
 
         *self.client.write().await = WrappedClient::Connected(client);
+        self.session.fetch_add(1, Ordering::SeqCst);
 
         let user_info = self.get_user_info().await?;
 
@@ -223,19 +917,16 @@ impl AppState {
     pub async fn connect_refresh_token(
         &self,
         refresh_token: impl Into<String>,
-    ) -> Result<(UserAccount, String), String> {
+    ) -> Result<(UserAccount, String), AppError> {
         let read_lock = self.client.read().await;
 
         let client_clone = match &*read_lock {
             WrappedClient::Disconnected(client) => client.clone(),
             WrappedClient::Connected(client) => {
                 let refresh_token = refresh_token.into();
-                return Ok((
-                    client.get_user_info().await.map_err(|e| e.to_string())?,
-                    refresh_token,
-                ));
+                return Ok((client.get_user_info().await?, refresh_token));
             }
-            WrappedClient::Unset => return Err("Client not initialized".to_string()),
+            WrappedClient::Unset => return Err(AppError::NotConnected),
         };
         // release read lock
         drop(read_lock);
@@ -243,56 +934,143 @@ impl AppState {
         let client = client_clone
             .connect(OAuth2Flow::refresh_token(refresh_token))
             .await
-            .map_err(|e| {
-                tracing::error!("Error connecting with refresh token: {e}");
-                e.to_string()
-            })?;
+            .map_err(|e| AppError::from(e).logged("Error connecting with refresh token"))?;
 
         let refresh_token = client.get_refresh_token().await.to_string();
 
         *self.client.write().await = WrappedClient::Connected(client);
+        self.session.fetch_add(1, Ordering::SeqCst);
 
         let user_info = self.get_user_info().await?;
 
         Ok((user_info, refresh_token))
     }
 
-    async fn get_user_info(&self) -> Result<UserAccount, String> {
+    async fn get_user_info(&self) -> Result<UserAccount, AppError> {
         let client = self.get_client().await?;
 
-        let user_info = client.get_user_info().await.map_err(|e| e.to_string())?;
+        let user_info = client.get_user_info().await?;
 
         Ok(user_info)
     }
 
-    pub async fn get_client(&self) -> Result<Dracoon<Connected>, String> {
+    pub async fn get_client(&self) -> Result<Dracoon<Connected>, AppError> {
         let read_lock = self.client.read().await;
 
         let client = match &*read_lock {
             WrappedClient::Connected(client) => client.clone(),
-            WrappedClient::Disconnected(_) => return Err("Client not connected".to_string()),
-            WrappedClient::Unset => return Err("Client not initialized".to_string()),
+            WrappedClient::Disconnected(_) | WrappedClient::Unset => {
+                return Err(AppError::NotConnected)
+            }
         };
 
         Ok(client)
     }
 
-    pub fn get_permissions_cache(
+    async fn get_session(&self) -> Result<(Dracoon<Connected>, u64), AppError> {
+        let session = self.session.load(Ordering::SeqCst);
+        let client = self.get_client().await?;
+        Ok((client, session))
+    }
+
+    /// Reconnects with the current refresh token unless another request already recovered the session
+    async fn recover_session(&self, session: u64) -> Result<(), AppError> {
+        let _recovery = self.recovery.lock().await;
+
+        if self.session.load(Ordering::SeqCst) != session {
+            tracing::debug!("Session already recovered");
+            return Ok(());
+        }
+
+        let refresh_token = self.get_client().await?.get_refresh_token().await.to_string();
+
+        let oauth_client = self.oauth_client().await?;
+        let redirect_uri = oauth_client.redirect_uri.clone();
+
+        let disconnected = build_client(&self.url, &oauth_client, redirect_uri)?;
+        let result = disconnected
+            .clone()
+            .connect(OAuth2Flow::refresh_token(refresh_token))
+            .await;
+
+        let client = match result {
+            Ok(client) => client,
+            Err(e) => {
+                let e = AppError::from(e).logged("Error reconnecting with refresh token");
+                // the stale session must not be used by further requests
+                *self.client.write().await = WrappedClient::Disconnected(disconnected);
+                *self.app_auth.write().await = AppAuth::Unset;
+                if let Some(Err(e)) = self.take_entry().await.map(|entry| entry.delete_dracoon_env()) {
+                    tracing::warn!("Error deleting invalid refresh token from keyring: {e}");
+                }
+                return Err(e);
+            }
+        };
+
+        let refresh_token = client.get_refresh_token().await.to_string();
+
+        *self.client.write().await = WrappedClient::Connected(client);
+        self.session.fetch_add(1, Ordering::SeqCst);
+
+        if let Some(entry) = &*self.entry.read().await {
+            entry.set_dracoon_env(&refresh_token).map_err(|e| {
+                tracing::error!("Error updating refresh token in keyring: {e}");
+                e
+            })?;
+        }
+
+        tracing::info!("Recovered session for profile {}", self.name);
+        Ok(())
+    }
+
+    async fn set_cache(&self, cache: AppCache) {
+        *self.cache.write().await = cache;
+    }
+
+    pub async fn get_permissions_cache(
         &self,
-    ) -> &Cache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>> {
-        &self.cache.permissions()
+    ) -> TrackedCache<PermissionsCacheKey, Arc<SerializedNodePermissionsList>> {
+        self.cache.read().await.permissions().clone()
+    }
+
+    pub async fn get_customer_cache(&self) -> TrackedCache<String, Arc<SerializedCustomerInfo>> {
+        self.cache.read().await.customer().clone()
+    }
+
+    pub async fn get_events_cache(&self) -> TrackedCache<EventsCacheKey, Arc<SerializedEventList>> {
+        self.cache.read().await.events().clone()
+    }
+
+    pub async fn get_operations_cache(
+        &self,
+    ) -> TrackedCache<String, Arc<SerializedOperationTypes>> {
+        self.cache.read().await.operations().clone()
+    }
+
+    pub async fn get_users_cache(&self) -> TrackedCache<UsersCacheKey, Arc<SerializedUserList>> {
+        self.cache.read().await.users().clone()
     }
 
-    pub fn get_customer_cache(&self) -> &Cache<String, Arc<SerializedCustomerInfo>> {
-        &self.cache.customer()
+    pub async fn get_groups_cache(&self) -> TrackedCache<GroupsCacheKey, Arc<SerializedGroupList>> {
+        self.cache.read().await.groups().clone()
     }
 
-    pub fn get_events_cache(&self) -> &Cache<EventsCacheKey, Arc<SerializedEventList>> {
-        &self.cache.events()
+    pub async fn get_group_cache(&self) -> TrackedCache<GroupCacheKey, Arc<SerializedGroupInfo>> {
+        self.cache.read().await.group().clone()
     }
 
-    pub fn get_operations_cache(&self) -> &Cache<String, Arc<SerializedOperationTypes>> {
-        &self.cache.operations()
+    pub async fn get_group_users_cache(
+        &self,
+    ) -> TrackedCache<GroupUsersCacheKey, Arc<SerializedGroupUserList>> {
+        self.cache.read().await.group_users().clone()
+    }
+
+    pub async fn cache_stats(&self) -> Vec<SerializedCacheStats> {
+        self.cache.read().await.stats().await
+    }
+
+    pub async fn invalidate_cache(&self, cache: Option<CacheName>, url: Option<&str>) -> u64 {
+        self.cache.read().await.invalidate(cache, url).await
     }
 }
 
@@ -311,44 +1089,33 @@ impl ListParams {
 }
 
 impl TryFrom<ListParams> for ListAllParams {
-    type Error = String;
+    type Error = AppError;
 
     fn try_from(value: ListParams) -> Result<Self, Self::Error> {
         let filter = value
             .filter
             .map(|filter| {
-                let filters = filter.split('|').collect::<Vec<&str>>();
-
-                let mut filter_results = Vec::new();
-
-                for filter in filters {
-                    let filter_parts = filter.split(':').collect::<Vec<&str>>();
-
-                    let field = filter_parts.first().ok_or("Filter field not found")?;
-                    let operator = filter_parts.get(1).ok_or("Filter operator not found")?;
-                    let operator = match *operator {
-                        "eq" => FilterOperator::Eq,
-                        "neq" => FilterOperator::Neq,
-                        "le" => FilterOperator::Le,
-                        "ge" => FilterOperator::Ge,
-                        "cn" => FilterOperator::Cn,
-                        _ => return Err("Invalid filter operator".to_string()),
-                    };
-                    let value = filter_parts.get(2).ok_or("Filter value not found")?;
-
-                    let filter = FilterQueryBuilder::new()
-                        .with_field(*field)
-                        .with_operator(operator)
-                        .with_value(*value)
-                        .try_build()
-                        .map_err(|e| e.to_string())?;
-
-                    filter_results.push(filter);
-                }
-
-                Ok(filter_results)
+                parse_filter(&filter)?
+                    .into_iter()
+                    .map(|expression| {
+                        let operator = match expression.operator {
+                            filter::FilterOperator::Eq => FilterOperator::Eq,
+                            filter::FilterOperator::Neq => FilterOperator::Neq,
+                            filter::FilterOperator::Le => FilterOperator::Le,
+                            filter::FilterOperator::Ge => FilterOperator::Ge,
+                            filter::FilterOperator::Cn => FilterOperator::Cn,
+                        };
+
+                        FilterQueryBuilder::new()
+                            .with_field(expression.field)
+                            .with_operator(operator)
+                            .with_value(expression.value)
+                            .try_build()
+                            .map_err(|e| AppError::InvalidInput(e.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, AppError>>()
             })
-            .and_then(|filter| filter.ok());
+            .transpose()?;
 
         let mut list_params = ListAllParams::builder();
 
@@ -370,6 +1137,56 @@ impl TryFrom<ListParams> for ListAllParams {
     }
 }
 
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedCacheStats {
+    pub name: String,
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub oldest_entry_age_secs: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandTiming {
+    pub command: String,
+    pub elapsed_ms: u64,
+    pub timestamp: String,
+    pub failed: bool,
+}
+
+/// Records the duration of a command on `finish` - a timer dropped before (e.g. by returning
+/// early with an error) records the command as failed
+pub struct CommandTimer<'a> {
+    state: &'a AppState,
+    command: &'static str,
+    started: Instant,
+    finished: bool,
+}
+
+impl CommandTimer<'_> {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn finish(mut self) {
+        self.finished = true;
+        self.state
+            .record_timing(self.command, self.started.elapsed().as_millis(), false);
+    }
+}
+
+impl Drop for CommandTimer<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.state
+                .record_timing(self.command, self.started.elapsed().as_millis(), true);
+        }
+    }
+}
+
 #[derive(Serialize, Clone)]
 pub struct Range {
     pub offset: u64,
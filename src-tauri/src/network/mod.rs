@@ -0,0 +1,98 @@
+use std::{future::Future, time::Duration};
+
+use tauri::State;
+
+use crate::{
+    config::{get_or_create_config_dir, APPLICATION_NAME},
+    error::AppError,
+    settings::{apply_settings, save_settings, AppSettings},
+    AppState,
+};
+
+pub use models::NetworkSettings;
+pub use retry::RetryPolicy;
+
+mod models;
+mod retry;
+
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+impl NetworkSettings {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.retry.max_attempts == 0 || self.retry.max_attempts > MAX_RETRY_ATTEMPTS {
+            return Err(AppError::InvalidInput(format!(
+                "Request attempts must be between 1 and {MAX_RETRY_ATTEMPTS}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(&self.retry)
+    }
+}
+
+/// HTTP client for requests not sent via dco3 (e.g. the PKCE token exchange)
+pub fn http_client(settings: &NetworkSettings) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder().user_agent(APPLICATION_NAME);
+
+    if let Some(timeout) = settings.timeout() {
+        builder = builder.timeout(timeout);
+    }
+
+    builder.build().map_err(|e| {
+        tracing::error!("Error building HTTP client: {e}");
+        AppError::Internal(e.to_string())
+    })
+}
+
+/// Runs a request with the configured timeout
+pub async fn with_timeout<T, E, Fut>(timeout: Option<Duration>, request: Fut) -> Result<T, AppError>
+where
+    Fut: Future<Output = Result<T, E>>,
+    AppError: From<E>,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return request.await.map_err(AppError::from),
+    };
+
+    match tokio::time::timeout(timeout, request).await {
+        Ok(result) => result.map_err(AppError::from),
+        Err(_) => {
+            tracing::error!("Request timed out after {} s", timeout.as_secs());
+            Err(AppError::Timeout)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_network_settings(state: State<'_, AppState>) -> Result<NetworkSettings, AppError> {
+    Ok(state.network_settings().await)
+}
+
+/// Saves and applies the network settings
+#[tauri::command]
+pub async fn update_network_settings(
+    settings: NetworkSettings,
+    state: State<'_, AppState>,
+) -> Result<NetworkSettings, AppError> {
+    settings.validate()?;
+
+    let config_dir = get_or_create_config_dir();
+    let previous = state.settings().await;
+    let app_settings = AppSettings {
+        network: settings.clone(),
+        ..previous.clone()
+    };
+    save_settings(&config_dir, &app_settings)?;
+    apply_settings(&config_dir, &previous, &app_settings, &state).await;
+
+    tracing::info!("Updated network settings");
+    Ok(settings)
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Timeout and retries of DRACOON requests.
+/// dco3 accepts no HTTP client configuration, so proxies and custom root certificates are not supported.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    pub timeout_secs: Option<u64>,
+    pub retry: RetrySettings,
+}
+
+/// Retries of the token exchange and of timed out DRACOON requests
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetrySettings {
+    /// attempts per request including the first one - 1 disables retries
+    pub max_attempts: u32,
+    /// delay before the first retry - doubled for every further retry
+    pub base_delay_ms: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_attempts: 5,
+            base_delay_ms: 500,
+            max_delay_secs: 30,
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+
+use crate::error::AppError;
+
+use super::models::RetrySettings;
+
+/// Retries failed requests with exponential backoff and jitter
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(settings: &RetrySettings) -> Self {
+        RetryPolicy {
+            max_attempts: settings.max_attempts.max(1),
+            base_delay: Duration::from_millis(settings.base_delay_ms),
+            max_delay: Duration::from_secs(settings.max_delay_secs),
+        }
+    }
+
+    /// Delay before the given retry (starting at 1) - a random value between half and the full backoff
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let backoff = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let backoff_ms = backoff.as_millis() as u64;
+
+        Duration::from_millis(rand::thread_rng().gen_range(backoff_ms / 2..=backoff_ms))
+    }
+
+    /// Runs the request until it succeeds, fails with an error that is not transient or all attempts are used.
+    /// A `Retry-After` of the server is waited for - if it exceeds the max. delay, the error is returned.
+    pub async fn run<T, F, Fut>(&self, msg: &str, request: F) -> Result<T, AppError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        self.run_if(msg, AppError::is_transient, request).await
+    }
+
+    /// Like `run`, but only errors accepted by `retryable` are retried
+    pub async fn run_if<T, F, Fut, R>(
+        &self,
+        msg: &str,
+        retryable: R,
+        mut request: F,
+    ) -> Result<T, AppError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+        R: Fn(&AppError) -> bool,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let e = match request().await {
+                Err(e) if retryable(&e) && attempt < self.max_attempts => e,
+                result => return result,
+            };
+
+            let delay = match e.retry_after() {
+                Some(retry_after) if retry_after > self.max_delay => {
+                    tracing::warn!(
+                        "{msg} - server asks to retry in {} s, more than the max. delay",
+                        retry_after.as_secs()
+                    );
+                    return Err(e);
+                }
+                Some(retry_after) => retry_after.max(self.delay(attempt)),
+                None => self.delay(attempt),
+            };
+
+            e.log_retry(msg, attempt, self.max_attempts, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{error::HttpErrorDetails, network::with_timeout};
+
+    fn policy(max_attempts: u32, base_delay_ms: u64, max_delay_secs: u64) -> RetryPolicy {
+        RetryPolicy::new(&RetrySettings {
+            max_attempts,
+            base_delay_ms,
+            max_delay_secs,
+        })
+    }
+
+    /// Local server answering with the given status and `Retry-After` per request - `200` once all are used
+    async fn mock_server(
+        responses: Vec<(u16, Option<&'static str>)>,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(_) => return,
+                };
+                let request = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, retry_after) = responses.get(request).copied().unwrap_or((200, None));
+
+                let mut buffer = [0u8; 4096];
+                let _ = socket.read(&mut buffer).await;
+
+                let retry_after = retry_after
+                    .map(|value| format!("Retry-After: {value}\r\n"))
+                    .unwrap_or_default();
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Length: 0\r\nConnection: close\r\n{retry_after}\r\n"
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (url, requests)
+    }
+
+    async fn get(client: &reqwest::Client, url: &str) -> Result<u16, AppError> {
+        let response = client.get(url).send().await?;
+
+        if response.status().is_success() {
+            return Ok(response.status().as_u16());
+        }
+
+        Err(
+            HttpErrorDetails::from_response(response, "Mock request failed")
+                .await
+                .into(),
+        )
+    }
+
+    async fn run(
+        policy: RetryPolicy,
+        responses: Vec<(u16, Option<&'static str>)>,
+    ) -> (Result<u16, AppError>, usize) {
+        let (address, requests) = mock_server(responses).await;
+        let url = format!("http://{address}");
+        let client = reqwest::Client::new();
+
+        let result = policy.run("Mock request", || get(&client, &url)).await;
+
+        (result, requests.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limits_and_server_errors() {
+        let responses = vec![(429, None), (503, None), (500, None)];
+        let (result, requests) = run(policy(5, 1, 1), responses).await;
+
+        assert_eq!(result, Ok(200));
+        assert_eq!(requests, 4);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        for status in [401, 404] {
+            let (result, requests) = run(policy(5, 1, 1), vec![(status, None)]).await;
+
+            assert_eq!(result.unwrap_err().status(), Some(status));
+            assert_eq!(requests, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stops_after_max_attempts() {
+        let responses = vec![(502, None); 5];
+        let (result, requests) = run(policy(3, 1, 1), responses).await;
+
+        assert_eq!(result.unwrap_err().status(), Some(502));
+        assert_eq!(requests, 3);
+    }
+
+    #[tokio::test]
+    async fn test_single_attempt_disables_retries() {
+        let (result, requests) = run(policy(1, 1, 1), vec![(503, None)]).await;
+
+        assert!(result.is_err());
+        assert_eq!(requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_retry_after() {
+        let started = Instant::now();
+        let (result, requests) = run(policy(3, 1, 5), vec![(429, Some("1"))]).await;
+
+        assert_eq!(result, Ok(200));
+        assert_eq!(requests, 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_if_retry_after_exceeds_max_delay() {
+        let (result, requests) = run(policy(3, 1, 1), vec![(503, Some("120"))]).await;
+
+        let e = result.unwrap_err();
+        assert_eq!(e.status(), Some(503));
+        assert_eq!(e.retry_after(), Some(Duration::from_secs(120)));
+        assert_eq!(requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_tls_failures() {
+        // the mock server does not speak TLS - the handshake fails
+        let (address, requests) = mock_server(Vec::new()).await;
+        let url = format!("https://{address}");
+        let client = reqwest::Client::new();
+
+        let result = policy(3, 1, 1)
+            .run("Mock request", || get(&client, &url))
+            .await;
+
+        assert!(matches!(result, Err(AppError::Tls(_))), "{result:?}");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_if_retries_timeouts_only() {
+        let attempts = AtomicUsize::new(0);
+
+        // first attempt hangs and is cancelled by the timeout
+        let result = policy(3, 1, 1)
+            .run_if("Mock request", AppError::is_timeout, || async {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                with_timeout(Some(Duration::from_millis(50)), async move {
+                    if attempt == 0 {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Ok::<_, AppError>(attempt)
+                })
+                .await
+            })
+            .await;
+
+        assert_eq!(result, Ok(1));
+
+        let (address, requests) = mock_server(vec![(503, None)]).await;
+        let url = format!("http://{address}");
+        let client = reqwest::Client::new();
+
+        let result = policy(3, 1, 1)
+            .run_if("Mock request", AppError::is_timeout, || get(&client, &url))
+            .await;
+
+        assert_eq!(result.unwrap_err().status(), Some(503));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_delay_is_within_backoff_bounds() {
+        let policy = policy(10, 100, 2);
+
+        for retry in 1..=10u32 {
+            let backoff =
+                Duration::from_millis(100 * 2u64.pow(retry - 1)).min(Duration::from_secs(2));
+
+            for _ in 0..50 {
+                let delay = policy.delay(retry);
+                assert!(
+                    delay >= backoff / 2,
+                    "retry {retry}: {delay:?} < {backoff:?} / 2"
+                );
+                assert!(delay <= backoff, "retry {retry}: {delay:?} > {backoff:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_delay_does_not_overflow() {
+        let policy = policy(10, 1000, 30);
+        assert!(policy.delay(u32::MAX) <= Duration::from_secs(30));
+    }
+}
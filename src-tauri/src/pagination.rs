@@ -0,0 +1,188 @@
+use std::future::Future;
+
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::{error::AppError, settings::ExportSettings};
+
+/// Max. items per request accepted by DRACOON
+pub const MAX_PAGE_SIZE: u64 = 500;
+
+/// A single page of a list
+pub struct Page<T> {
+    pub total: u64,
+    pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+    pub fn new(total: u64, items: Vec<T>) -> Self {
+        Page { total, items }
+    }
+}
+
+/// Fetches all pages of a list with several requests in flight - pages are processed in order
+#[derive(Clone, Copy, Debug)]
+pub struct Paginator {
+    page_size: u64,
+    concurrency: usize,
+    start_offset: u64,
+}
+
+impl Paginator {
+    pub fn new(page_size: u64, concurrency: usize) -> Self {
+        Paginator {
+            page_size: page_size.clamp(1, MAX_PAGE_SIZE),
+            concurrency: concurrency.max(1),
+            start_offset: 0,
+        }
+    }
+
+    /// Skips the items before the offset - used to resume an export
+    pub fn starting_at(self, start_offset: u64) -> Self {
+        Paginator {
+            start_offset,
+            ..self
+        }
+    }
+
+    /// Fetches the first page (at the start offset) to get the total, then all remaining pages concurrently.
+    /// `fetch_page` is called with offset and limit, `on_page` receives the pages in list order.
+    pub async fn for_each_page<T, F, Fut, C>(
+        &self,
+        fetch_page: F,
+        mut on_page: C,
+    ) -> Result<u64, AppError>
+    where
+        F: Fn(u64, u64) -> Fut,
+        Fut: Future<Output = Result<Page<T>, AppError>>,
+        C: FnMut(Page<T>) -> Result<(), AppError>,
+    {
+        let first_page = fetch_page(self.start_offset, self.page_size).await?;
+        let total = first_page.total;
+        on_page(first_page)?;
+
+        let offsets = (self.start_offset + self.page_size..total).step_by(self.page_size as usize);
+        let mut pages = stream::iter(offsets)
+            .map(|offset| {
+                tracing::debug!("Fetching page with offset {offset}");
+                fetch_page(offset, self.page_size)
+            })
+            .buffered(self.concurrency);
+
+        while let Some(page) = pages.try_next().await? {
+            on_page(page)?;
+        }
+
+        Ok(total)
+    }
+}
+
+impl From<&ExportSettings> for Paginator {
+    fn from(value: &ExportSettings) -> Self {
+        Paginator::new(value.page_size, value.concurrency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    /// Serves pages of `0..total` and records the requested offsets and limits
+    fn fetch_numbers(
+        total: u64,
+        requests: &RefCell<Vec<(u64, u64)>>,
+    ) -> impl Fn(u64, u64) -> std::future::Ready<Result<Page<u64>, AppError>> + '_ {
+        move |offset, limit| {
+            requests.borrow_mut().push((offset, limit));
+            let items = (offset..(offset + limit).min(total)).collect();
+            std::future::ready(Ok(Page::new(total, items)))
+        }
+    }
+
+    fn collect(paginator: Paginator, total: u64) -> (Vec<u64>, Vec<(u64, u64)>) {
+        let requests = RefCell::new(Vec::new());
+        let mut items = Vec::new();
+
+        let fetched_total = block_on(paginator.for_each_page(
+            fetch_numbers(total, &requests),
+            |page| {
+                items.extend(page.items);
+                Ok(())
+            },
+        ))
+        .unwrap();
+
+        assert_eq!(fetched_total, total);
+        (items, requests.into_inner())
+    }
+
+    #[test]
+    fn test_fetches_all_pages_in_order() {
+        let (items, requests) = collect(Paginator::new(10, 3), 35);
+
+        assert_eq!(items, (0..35).collect::<Vec<_>>());
+        assert_eq!(requests, vec![(0, 10), (10, 10), (20, 10), (30, 10)]);
+    }
+
+    #[test]
+    fn test_single_page() {
+        let (items, requests) = collect(Paginator::new(500, 4), 3);
+
+        assert_eq!(items, vec![0, 1, 2]);
+        assert_eq!(requests, vec![(0, 500)]);
+    }
+
+    #[test]
+    fn test_empty_list() {
+        let (items, requests) = collect(Paginator::new(100, 4), 0);
+
+        assert!(items.is_empty());
+        assert_eq!(requests, vec![(0, 100)]);
+    }
+
+    #[test]
+    fn test_starting_at_offset() {
+        let (items, requests) = collect(Paginator::new(10, 2).starting_at(15), 32);
+
+        assert_eq!(items, (15..32).collect::<Vec<_>>());
+        assert_eq!(requests, vec![(15, 10), (25, 10)]);
+    }
+
+    #[test]
+    fn test_page_size_and_concurrency_are_bounded() {
+        let (_, requests) = collect(Paginator::new(10_000, 0), 1200);
+        assert_eq!(requests, vec![(0, 500), (500, 500), (1000, 500)]);
+
+        let (_, requests) = collect(Paginator::new(0, 1), 2);
+        assert_eq!(requests, vec![(0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn test_stops_on_error() {
+        let requests = RefCell::new(Vec::new());
+        let mut pages = 0;
+
+        let result = block_on(Paginator::new(10, 1).for_each_page(
+            |offset, limit| {
+                requests.borrow_mut().push((offset, limit));
+                let page = if offset == 10 {
+                    Err(AppError::Internal("page failed".to_string()))
+                } else {
+                    Ok(Page::new(30, vec![offset; limit as usize]))
+                };
+                std::future::ready(page)
+            },
+            |_| {
+                pages += 1;
+                Ok(())
+            },
+        ));
+
+        assert!(result.is_err());
+        assert_eq!(pages, 1);
+        assert_eq!(requests.into_inner(), vec![(0, 10), (10, 10)]);
+    }
+}
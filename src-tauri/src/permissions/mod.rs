@@ -1,8 +1,16 @@
 mod models;
-use std::{sync::Arc, time::Instant};
-
-use crate::{config::log_dracoon_error, models::ListParams, AppState};
+use std::{collections::HashSet, sync::Arc, time::Instant};
+
+use crate::{
+    error::AppError,
+    guard::{require_roles, GuardedCommand},
+    jobs::{enqueue_export, ExportRequest, Job},
+    models::{ListParams, Profile},
+    pagination::{Page, Paginator},
+    AppState,
+};
 use dco3::{eventlog::AuditNodesFilter, Eventlog, ListAllParams, Users};
+use futures::{stream, StreamExt, TryStreamExt};
 use models::{AuditNodeListWrapper, FlattenedNodePermissions};
 use tauri::State;
 
@@ -12,28 +20,37 @@ pub use models::{PermissionsCacheKey, SerializedNodePermissionsList};
 #[allow(deprecated)]
 pub async fn get_permissions(
     params: ListParams,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<SerializedNodePermissionsList, String> {
-    let now = Instant::now();
+) -> Result<SerializedNodePermissionsList, AppError> {
+    require_roles(&state, GuardedCommand::GetPermissions).await?;
+    let timer = state.timer("get_permissions");
     let client = state.get_client().await?;
 
     let url = client.get_base_url().to_string();
     let key = PermissionsCacheKey::new(url, params.clone());
 
-    if let Some(permissions) = state.get_permissions_cache().get(&key).await {
-        let elapsed = now.elapsed().as_millis();
+    let cached = if force_refresh.unwrap_or_default() {
+        None
+    } else {
+        state.get_permissions_cache().await?.get(&key).await
+    };
+
+    if let Some(permissions) = cached {
+        let elapsed = timer.elapsed().as_millis();
         tracing::info!("Fetched cached permissions in {elapsed} ms");
+        timer.finish();
         return Ok((*permissions).clone());
     }
 
-    let permissions = client
-        .eventlog()
-        .get_node_permissions(params.try_into()?)
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching permissions"));
-            e.to_string()
-        })?;
+    let params: ListAllParams = params.try_into()?;
+
+    let permissions = state
+        .call("Error fetching permissions", |client| {
+            let params = params.clone();
+            async move { client.eventlog().get_node_permissions(params).await }
+        })
+        .await?;
 
     let wrapped_permissions: AuditNodeListWrapper = permissions.into();
     let serializable_permissions: SerializedNodePermissionsList = wrapped_permissions.into();
@@ -41,40 +58,76 @@ pub async fn get_permissions(
 
     state
         .get_permissions_cache()
+        .await?
         .insert(key, serializable_permissions.clone())
         .await;
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched permissions in {elapsed} ms");
+    timer.finish();
     Ok((*serializable_permissions).clone())
 }
 
 #[tauri::command]
-#[allow(deprecated)]
 pub async fn export_user_permissions(
     params: ListParams,
     path: String,
+    force_refresh: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportUserPermissions).await?;
+
+    let request = ExportRequest::UserPermissions {
+        params,
+        force_refresh: force_refresh.unwrap_or_default(),
+    };
+
+    enqueue_export(&state, request, path).await
+}
+
+#[tauri::command]
+pub async fn export_all_user_permissions(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportAllUserPermissions).await?;
+
+    enqueue_export(&state, ExportRequest::AllUserPermissions, path).await
+}
+
+/// Writes the permissions matching the params - returns the number of written rows
+#[allow(deprecated)]
+pub(crate) async fn write_user_permissions(
+    state: &AppState,
+    params: ListParams,
+    force_refresh: bool,
+    job: &mut Job,
+) -> Result<u64, AppError> {
     let now = Instant::now();
-    let client = state.get_client().await?;
+    let export = state.settings().await.export;
+    let profile = job.profile();
+    let client = profile.get_client().await?;
 
     let url = client.get_base_url().to_string();
     let key = PermissionsCacheKey::new(url, params.clone());
 
-    let serializable_permissions = if let Some(permissions) =
-        state.get_permissions_cache().get(&key).await
-    {
+    let cached = if force_refresh {
+        None
+    } else {
+        profile.get_permissions_cache().await.get(&key).await
+    };
+
+    let serializable_permissions = if let Some(permissions) = cached {
         (*permissions).clone()
     } else {
-        let fetched_permissions = client
-            .eventlog()
-            .get_node_permissions(params.try_into()?)
-            .await
-            .map_err(|e| {
-                log_dracoon_error(&e, Some("Error fetching permissions"));
-                e.to_string()
-            })?;
+        let params: ListAllParams = params.try_into()?;
+
+        let fetched_permissions = state
+            .call_with(&profile, "Error fetching permissions", |client| {
+                let params = params.clone();
+                async move { client.eventlog().get_node_permissions(params).await }
+            })
+            .await?;
 
         let wrapped_permissions: AuditNodeListWrapper = fetched_permissions.into();
         let serializable_permissions: SerializedNodePermissionsList = wrapped_permissions.into();
@@ -82,128 +135,137 @@ pub async fn export_user_permissions(
     };
     let elapsed_fetched_events = now.elapsed().as_millis();
     tracing::info!("Fetched permissions in {elapsed_fetched_events} ms");
+    job.page_fetched(0)?;
 
-    let flattened_permissions: Vec<FlattenedNodePermissions> = serializable_permissions
-        .into_iter()
-        .map(|perms| Into::<Vec<FlattenedNodePermissions>>::into(perms))
-        .flatten()
-        .collect();
-
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {}", e);
-        e.to_string()
-    })?;
-
-    for event in flattened_permissions {
-        csv_writer.serialize(event).map_err(|e| {
-            tracing::error!("Error serializing event: {}", e);
-            e.to_string()
-        })?;
-    }
-
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {}", e);
-        e.to_string()
-    })?;
+    let mut writer = job.writer(export.format)?;
+    writer.write_items(
+        serializable_permissions
+            .into_iter()
+            .flat_map(Into::<Vec<FlattenedNodePermissions>>::into),
+    )?;
+    let permission_count = writer.finish()?;
 
     let elapsed_exported_events = now.elapsed().as_millis();
-    tracing::info!("Exported permissions in {elapsed_exported_events} ms");
-    Ok(())
+    tracing::info!("Exported {permission_count} permissions in {elapsed_exported_events} ms");
+    Ok(permission_count)
 }
 
-#[tauri::command]
+/// Writes the permissions of all users user by user - returns the number of written rows
 #[allow(deprecated)]
-pub async fn export_all_user_permissions(
-    path: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
+pub(crate) async fn write_all_user_permissions(
+    state: &AppState,
+    job: &mut Job,
+) -> Result<u64, AppError> {
     let now = Instant::now();
-    let mut users = state
-        .get_client()
-        .await?
-        .users()
-        .get_users(None, None, None)
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching users"));
-            e.to_string()
-        })?;
-
-    if users.range.total > 500 {
-        for offset in (500..users.range.total).step_by(500) {
-            let params = ListAllParams::builder().with_offset(offset).build();
-            let new_users = state
-                .get_client()
-                .await?
-                .users()
-                .get_users(Some(params), None, None)
-                .await
-                .map_err(|e| {
-                    log_dracoon_error(&e, Some("Error fetching users"));
-                    e.to_string()
-                })?;
-
-            users.items.extend(new_users.items);
-        }
-    }
-
-    let user_ids = users
-        .items
-        .into_iter()
-        .map(|user| user.id)
-        .collect::<Vec<_>>();
+    let export = state.settings().await.export;
+    let paginator: Paginator = (&export).into();
+    let profile = job.profile();
+    // client and network settings are resolved once for all requests of the export
+    let client = &state.profile_client(&profile).await?;
+
+    let mut user_ids = Vec::new();
+
+    let user_count = paginator
+        .for_each_page(
+            |offset, limit| {
+                let params = ListAllParams::builder()
+                    .with_offset(offset)
+                    .with_limit(limit)
+                    .build();
+
+                async move {
+                    let users = client
+                        .call("Error fetching users", |client| {
+                            let params = params.clone();
+                            async move { client.users().get_users(Some(params), None, None).await }
+                        })
+                        .await?;
+
+                    Ok(Page::new(
+                        users.range.total,
+                        users.items.into_iter().map(|user| user.id).collect(),
+                    ))
+                }
+            },
+            |page| {
+                user_ids.extend(page.items);
+                job.page_fetched(0)
+            },
+        )
+        .await?;
 
     let elapsed_fetched_users = now.elapsed().as_millis();
-    tracing::info!("Fetched all users in {elapsed_fetched_users} ms");
-
-    let mut node_permissions = Vec::new();
-
-    for user_id in user_ids {
-        let user_filter = AuditNodesFilter::user_id_equals(user_id);
-        let params = ListAllParams::builder().with_filter(user_filter).build();
-
-        let permissions = state
-            .get_client()
-            .await?
-            .eventlog()
-            .get_node_permissions(params)
-            .await
-            .map_err(|e| {
-                log_dracoon_error(&e, Some("Error fetching permissions"));
-                e.to_string()
-            })?;
-
-        node_permissions.extend(permissions);
-    }
-    let elapsed_fetched_permissions = now.elapsed().as_millis();
-    tracing::info!("Fetched all permissions in {elapsed_fetched_permissions} ms");
+    tracing::info!("Fetched {user_count} users in {elapsed_fetched_users} ms");
 
-    let wrapped_permissions: AuditNodeListWrapper = node_permissions.into();
-    let serializable_permissions: SerializedNodePermissionsList = wrapped_permissions.into();
-    let flattened_permissions: Vec<FlattenedNodePermissions> = serializable_permissions
-        .into_iter()
-        .map(|perms| Into::<Vec<FlattenedNodePermissions>>::into(perms))
-        .flatten()
-        .collect();
-
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {e}");
-        e.to_string()
-    })?;
-
-    for event in flattened_permissions {
-        csv_writer.serialize(event).map_err(|e| {
-            tracing::error!("Error serializing event: {e}");
-            e.to_string()
-        })?;
+    // users are processed by id - a resumed export continues after the last written user
+    user_ids.sort_unstable();
+    if let Some(last_id) = job.resume_last_id() {
+        user_ids.retain(|user_id| *user_id > last_id);
+    }
+    job.set_total(user_ids.len() as u64);
+
+    let mut writer = job.writer(export.format)?;
+
+    // permissions of several users are fetched in parallel - results are written in user order
+    let mut permissions = stream::iter(user_ids)
+        .map(|user_id| async move {
+            let user_filter = AuditNodesFilter::user_id_equals(user_id);
+            let params = ListAllParams::builder().with_filter(user_filter).build();
+
+            let permissions = client
+                .call("Error fetching permissions", |client| {
+                    let params = params.clone();
+                    async move { client.eventlog().get_node_permissions(params).await }
+                })
+                .await?;
+
+            let wrapped_permissions: AuditNodeListWrapper = permissions.into();
+            let serializable_permissions: SerializedNodePermissionsList =
+                wrapped_permissions.into();
+            Ok::<_, AppError>((user_id, serializable_permissions))
+        })
+        .buffered(export.permissions_concurrency.max(1));
+
+    // a node is returned for each of its users - every node and user is written once
+    let mut written_permissions: HashSet<(i64, i64)> = HashSet::new();
+
+    while let Some((user_id, serializable_permissions)) = permissions.try_next().await? {
+        // permissions are written per user and not kept in memory
+        writer.write_items(
+            serializable_permissions
+                .into_iter()
+                .flat_map(Into::<Vec<FlattenedNodePermissions>>::into)
+                .filter(|permission| {
+                    written_permissions.insert((permission.node_id, permission.user_id))
+                }),
+        )?;
+
+        job.item_written(&mut writer, user_id)?;
     }
 
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {e}");
-        e.to_string()
-    })?;
+    let permission_count = writer.finish()?;
+
     let elapsed_exported_permissions = now.elapsed().as_millis();
-    tracing::info!("Exported all permissions in {elapsed_exported_permissions} ms");
+    tracing::info!("Exported {permission_count} permissions in {elapsed_exported_permissions} ms");
 
-    Ok(())
+    Ok(permission_count)
+}
+
+/// Fetches the permissions of all nodes
+#[allow(deprecated)]
+pub(crate) async fn get_all_permissions(
+    state: &AppState,
+    profile: &Profile,
+) -> Result<SerializedNodePermissionsList, AppError> {
+    let permissions = state
+        .call_with(profile, "Error fetching permissions", |client| async move {
+            client
+                .eventlog()
+                .get_node_permissions(ListAllParams::builder().build())
+                .await
+        })
+        .await?;
+
+    let wrapped_permissions: AuditNodeListWrapper = permissions.into();
+    Ok(wrapped_permissions.into())
 }
@@ -6,7 +6,7 @@ use dco3::{
 };
 use serde::Serialize;
 
-use crate::models::ListParams;
+use crate::{cache::CacheKey, models::ListParams};
 
 pub type SerializedNodePermissionsList = Vec<SerializedNodePermissions>;
 
@@ -31,6 +31,12 @@ impl From<&PermissionsCacheKey> for String {
     }
 }
 
+impl CacheKey for PermissionsCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
 impl Hash for PermissionsCacheKey {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let key: String = self.into();
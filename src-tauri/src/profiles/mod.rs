@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use crate::{
+    auth,
+    config::{get_or_create_config_dir, OAuthClientConfig},
+    error::AppError,
+    AppState,
+};
+use models::{SerializedOAuthClient, SerializedProfile};
+use tauri::State;
+
+pub use models::{StoredOAuthClient, StoredProfile};
+
+mod models;
+
+const PROFILES_FILE: &str = "profiles.json";
+
+pub fn load_profiles(config_dir: &Path) -> Vec<StoredProfile> {
+    let path = config_dir.join(PROFILES_FILE);
+
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    std::fs::read_to_string(&path)
+        .map_err(AppError::from)
+        .and_then(|content| serde_json::from_str(&content).map_err(AppError::from))
+        .unwrap_or_else(|e| {
+            tracing::error!("Error loading profiles: {e}");
+            Vec::new()
+        })
+}
+
+pub async fn save_profiles(state: &AppState) -> Result<(), AppError> {
+    let path = get_or_create_config_dir().join(PROFILES_FILE);
+    let profiles = state.stored_profiles().await;
+
+    let content = serde_json::to_string_pretty(&profiles).map_err(|e| {
+        tracing::error!("Error serializing profiles: {e}");
+        AppError::from(e)
+    })?;
+
+    std::fs::write(path, content).map_err(|e| {
+        tracing::error!("Error saving profiles: {e}");
+        AppError::from(e)
+    })
+}
+
+#[tauri::command]
+pub async fn get_profiles(state: State<'_, AppState>) -> Result<Vec<SerializedProfile>, AppError> {
+    let active_profile = state.active_profile_name().await;
+    let mut profiles = Vec::new();
+
+    for profile in state.profiles().await {
+        profiles.push(SerializedProfile::new(&profile, active_profile.as_deref()).await);
+    }
+
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub async fn add_profile(
+    name: String,
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<SerializedProfile, AppError> {
+    let profile = state.get_or_create_profile(&name, &url).await?;
+    save_profiles(&state).await?;
+
+    tracing::info!("Added profile {name}");
+    let active_profile = state.active_profile_name().await;
+    Ok(SerializedProfile::new(&profile, active_profile.as_deref()).await)
+}
+
+#[tauri::command]
+pub async fn switch_profile(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<SerializedProfile, AppError> {
+    let timer = state.timer("switch_profile");
+    let profile = state.set_active_profile(&name).await?;
+
+    let elapsed = timer.elapsed().as_millis();
+    tracing::info!("Switched to profile {name} in {elapsed} ms");
+    timer.finish();
+    Ok(SerializedProfile::new(&profile, Some(&name)).await)
+}
+
+#[tauri::command]
+pub async fn remove_profile(name: String, state: State<'_, AppState>) -> Result<(), AppError> {
+    let profile = state.remove_profile(&name).await?;
+
+    if let Err(e) = profile.logout().await {
+        tracing::warn!("Error revoking tokens for profile {name}: {e}");
+    }
+
+    // a profile that never logged in has no stored refresh token
+    if let Err(e) = auth::delete_refresh_token(&state, &profile).await {
+        tracing::warn!("Error deleting refresh token for profile {name}: {e}");
+    }
+
+    if profile.custom_oauth_client().await.is_some() {
+        if let Err(e) = auth::store_client_secret(&state, &profile, None).await {
+            tracing::warn!("Error deleting OAuth client secret for profile {name}: {e}");
+        }
+    }
+
+    save_profiles(&state).await?;
+
+    tracing::info!("Removed profile {name}");
+    Ok(())
+}
+
+/// Custom OAuth client of a profile without its secret - `None` if the bundled client is used
+#[tauri::command]
+pub async fn get_oauth_client(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<Option<SerializedOAuthClient>, AppError> {
+    let profile = state.get_profile(&name).await?;
+
+    let oauth_client = match profile.custom_oauth_client().await {
+        Some(oauth_client) => oauth_client,
+        None => return Ok(None),
+    };
+
+    let has_client_secret = auth::load_client_secret(&state, &profile).await.is_ok();
+
+    Ok(Some(SerializedOAuthClient {
+        client_id: oauth_client.client_id,
+        redirect_uri: oauth_client.redirect_uri,
+        has_client_secret,
+    }))
+}
+
+/// Sets the OAuth client of a profile - `None` resets it to the bundled client.
+/// An empty secret keeps the secret already stored for the profile.
+#[tauri::command]
+pub async fn set_oauth_client(
+    name: String,
+    oauth_client: Option<OAuthClientConfig>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let profile = state.get_profile(&name).await?;
+
+    match oauth_client {
+        Some(mut oauth_client) => {
+            if oauth_client.client_id.is_empty() {
+                tracing::error!("Invalid OAuth client for profile {name}");
+                return Err(AppError::InvalidInput("Client id is required".to_string()));
+            }
+
+            if oauth_client.client_secret.is_empty() {
+                auth::load_client_secret(&state, &profile)
+                    .await
+                    .map_err(|_| AppError::InvalidInput("Client secret is required".to_string()))?;
+                oauth_client.client_secret = profile.oauth_client().await?.client_secret;
+            }
+
+            auth::store_client_secret(&state, &profile, Some(&oauth_client.client_secret)).await?;
+            profile.set_oauth_client(Some(oauth_client)).await;
+        }
+        None => {
+            if profile.custom_oauth_client().await.is_some() {
+                if let Err(e) = auth::store_client_secret(&state, &profile, None).await {
+                    tracing::warn!("Error deleting OAuth client secret for profile {name}: {e}");
+                }
+            }
+
+            profile.set_oauth_client(None).await;
+        }
+    }
+
+    save_profiles(&state).await?;
+
+    tracing::info!("Updated OAuth client of profile {name}");
+    Ok(())
+}
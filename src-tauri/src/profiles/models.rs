@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Profile;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredProfile {
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth_client: Option<StoredOAuthClient>,
+}
+
+/// Custom OAuth client of a profile - the secret is kept in the credential store
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredOAuthClient {
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedOAuthClient {
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    pub has_client_secret: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedProfile {
+    pub name: String,
+    pub url: String,
+    pub is_connected: bool,
+    pub is_active: bool,
+}
+
+impl SerializedProfile {
+    pub async fn new(profile: &Profile, active_profile: Option<&str>) -> Self {
+        SerializedProfile {
+            name: profile.name().to_string(),
+            url: profile.url().to_string(),
+            is_connected: profile.is_connected().await,
+            is_active: active_profile == Some(profile.name()),
+        }
+    }
+}
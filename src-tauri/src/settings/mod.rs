@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use tauri::State;
+use toml::Table;
+
+use crate::{
+    config::get_or_create_config_dir,
+    error::AppError,
+    logs::{cleanup_rotated_logs, Redactor},
+    pagination::MAX_PAGE_SIZE,
+    AppState,
+};
+
+pub use models::{
+    AppSettings, CacheSettings, ExportFormat, ExportSettings, LogFormat, LogLevel, LogSettings,
+    RedactionSettings,
+};
+use models::{SerializedSettingsUpdate, SETTINGS_VERSION};
+
+mod models;
+
+const SETTINGS_FILE: &str = "settings.toml";
+const MAX_EXPORT_CONCURRENCY: usize = 8;
+const MAX_RUNNING_JOBS: usize = 4;
+const MAX_PERMISSIONS_CONCURRENCY: usize = 16;
+const MAX_LOG_SIZE_MB: u64 = 1024;
+const MAX_LOG_AGE_DAYS: u64 = 3650;
+
+/// Migrations of the settings table - index 0 migrates version 1 to 2 and so on.
+/// Add a migration for every bump of `SETTINGS_VERSION`.
+const MIGRATIONS: &[fn(&mut Table)] = &[];
+
+/// Loads the settings file - settings of previous versions are migrated and saved
+pub fn load_settings(config_dir: &Path) -> Result<AppSettings, AppError> {
+    let path = config_dir.join(SETTINGS_FILE);
+
+    if !path.exists() {
+        let settings = AppSettings::default();
+        save_settings(config_dir, &settings)?;
+        return Ok(settings);
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    let mut table: Table = toml::from_str(&content)
+        .map_err(|e| AppError::Internal(format!("Invalid settings file: {e}")))?;
+
+    // files without version are treated as the first version
+    let version = table
+        .get("version")
+        .and_then(|version| version.as_integer())
+        .map(|version| version.max(1) as u32)
+        .unwrap_or(1);
+
+    if version > SETTINGS_VERSION {
+        return Err(AppError::Internal(format!(
+            "Settings version {version} is not supported"
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().skip(version as usize - 1) {
+        migration(&mut table);
+    }
+    table.insert(
+        "version".to_string(),
+        toml::Value::Integer(SETTINGS_VERSION.into()),
+    );
+
+    let settings: AppSettings = toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| AppError::Internal(format!("Invalid settings file: {e}")))?;
+
+    if version < SETTINGS_VERSION {
+        tracing::info!("Migrated settings from version {version} to {SETTINGS_VERSION}");
+        save_settings(config_dir, &settings)?;
+    }
+
+    Ok(settings)
+}
+
+pub fn save_settings(config_dir: &Path, settings: &AppSettings) -> Result<(), AppError> {
+    let content = toml::to_string_pretty(settings)
+        .map_err(|e| AppError::Internal(format!("Error serializing settings: {e}")))?;
+
+    // write to a temporary file first to never leave a truncated settings file behind
+    let path = config_dir.join(SETTINGS_FILE);
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, content)
+        .and_then(|_| std::fs::rename(&tmp_path, &path))
+        .map_err(|e| {
+            tracing::error!("Error saving settings: {e}");
+            AppError::from(e)
+        })
+}
+
+impl AppSettings {
+    fn validate(&self) -> Result<(), AppError> {
+        if self.export.page_size == 0 || self.export.page_size > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidInput(format!(
+                "Page size must be between 1 and {MAX_PAGE_SIZE}"
+            )));
+        }
+
+        if self.export.concurrency == 0 || self.export.concurrency > MAX_EXPORT_CONCURRENCY {
+            return Err(AppError::InvalidInput(format!(
+                "Export concurrency must be between 1 and {MAX_EXPORT_CONCURRENCY}"
+            )));
+        }
+
+        if self.export.max_running_jobs == 0 || self.export.max_running_jobs > MAX_RUNNING_JOBS {
+            return Err(AppError::InvalidInput(format!(
+                "Running exports must be between 1 and {MAX_RUNNING_JOBS}"
+            )));
+        }
+
+        if self.export.permissions_concurrency == 0
+            || self.export.permissions_concurrency > MAX_PERMISSIONS_CONCURRENCY
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Permissions concurrency must be between 1 and {MAX_PERMISSIONS_CONCURRENCY}"
+            )));
+        }
+
+        if self.log.max_size_mb == 0 || self.log.max_size_mb > MAX_LOG_SIZE_MB {
+            return Err(AppError::InvalidInput(format!(
+                "Log file size must be between 1 and {MAX_LOG_SIZE_MB} MB"
+            )));
+        }
+
+        if self.log.max_age_days > MAX_LOG_AGE_DAYS {
+            return Err(AppError::InvalidInput(format!(
+                "Log file age must be at most {MAX_LOG_AGE_DAYS} days"
+            )));
+        }
+
+        Redactor::new(&self.log.redaction)?;
+
+        if self.cache.max_entries == 0 {
+            return Err(AppError::InvalidInput(
+                "Cache size must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(directory) = &self.export.default_directory {
+            if !Path::new(directory).is_dir() {
+                return Err(AppError::InvalidInput(format!(
+                    "Export directory does not exist: {directory}"
+                )));
+            }
+        }
+
+        self.network.validate()
+    }
+}
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, AppError> {
+    Ok(state.settings().await)
+}
+
+/// Saves and applies the settings - changes that cannot be applied at runtime are flagged
+#[tauri::command]
+pub async fn update_settings(
+    settings: AppSettings,
+    state: State<'_, AppState>,
+) -> Result<SerializedSettingsUpdate, AppError> {
+    let previous = state.settings().await;
+    let settings = AppSettings {
+        version: SETTINGS_VERSION,
+        ..settings
+    };
+    settings.validate()?;
+
+    let config_dir = get_or_create_config_dir();
+    save_settings(&config_dir, &settings)?;
+
+    let restart_required = apply_settings(&config_dir, &previous, &settings, &state).await;
+
+    tracing::info!("Updated settings (restart required: {restart_required})");
+    Ok(SerializedSettingsUpdate {
+        settings,
+        restart_required,
+    })
+}
+
+/// Applies changed settings at runtime - returns true if a restart is required for some of them
+pub async fn apply_settings(
+    config_dir: &Path,
+    previous: &AppSettings,
+    settings: &AppSettings,
+    state: &AppState,
+) -> bool {
+    state.set_settings(settings.clone()).await;
+
+    if previous.cache != settings.cache {
+        state.rebuild_caches(&settings.cache).await;
+    }
+
+    if previous.log.level != settings.log.level {
+        state.set_log_level(settings.log.level);
+    }
+
+    if previous.log.redaction != settings.log.redaction {
+        state.set_log_redaction(&settings.log.redaction);
+    }
+
+    if previous.log.max_files != settings.log.max_files
+        || previous.log.max_age_days != settings.log.max_age_days
+    {
+        for warning in cleanup_rotated_logs(config_dir, &settings.log) {
+            tracing::warn!("{warning}");
+        }
+    }
+
+    if previous.credential_backend != settings.credential_backend {
+        state
+            .credentials()
+            .set_backend(settings.credential_backend)
+            .await;
+    }
+
+    previous.log.max_size_mb != settings.log.max_size_mb
+        || previous.log.compress != settings.log.compress
+        || previous.log.format != settings.log.format
+        || previous.export.max_running_jobs != settings.export.max_running_jobs
+}
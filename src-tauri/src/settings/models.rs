@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{auth::CredentialBackend, network::NetworkSettings};
+
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Settings stored in `settings.toml` - missing values fall back to the defaults
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    pub version: u32,
+    pub cache: CacheSettings,
+    pub export: ExportSettings,
+    pub log: LogSettings,
+    pub network: NetworkSettings,
+    pub credential_backend: CredentialBackend,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CacheSettings {
+    /// TTL of cached lists (users, groups, permissions)
+    pub ttl_secs: u64,
+    pub events_ttl_secs: u64,
+    /// TTL of rarely changing data (customer info, operation types)
+    pub static_ttl_secs: u64,
+    pub max_entries: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportSettings {
+    /// items fetched per request (max. 500)
+    pub page_size: u64,
+    /// pages fetched in parallel
+    pub concurrency: usize,
+    /// exports running at the same time - further exports are queued
+    pub max_running_jobs: usize,
+    /// users whose permissions are fetched in parallel (all user permissions)
+    pub permissions_concurrency: usize,
+    pub default_directory: Option<String>,
+    pub format: ExportFormat,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogSettings {
+    pub level: LogLevel,
+    /// size of the log file before it is rotated on startup
+    pub max_size_mb: u64,
+    /// rotated log files to keep
+    pub max_files: usize,
+    /// rotated log files older than this are deleted (0 keeps them regardless of age)
+    pub max_age_days: u64,
+    /// gzip rotated log files
+    pub compress: bool,
+    pub format: LogFormat,
+    pub redaction: RedactionSettings,
+}
+
+/// Masking of personal data and secrets in log output
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionSettings {
+    pub emails: bool,
+    /// tokens, passwords and credentials in urls
+    pub secrets: bool,
+    /// logins, first and last names, home directory
+    pub user_names: bool,
+    /// node, file and room names
+    pub node_names: bool,
+    /// additional regular expressions - matches are masked
+    pub patterns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    Text,
+    /// one JSON object per line
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSettingsUpdate {
+    pub settings: AppSettings,
+    /// some changes (e.g. log file size) only apply after a restart
+    pub restart_required: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            version: SETTINGS_VERSION,
+            cache: CacheSettings::default(),
+            export: ExportSettings::default(),
+            log: LogSettings::default(),
+            network: NetworkSettings::default(),
+            credential_backend: CredentialBackend::default(),
+        }
+    }
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            ttl_secs: 5 * 60,
+            events_ttl_secs: 60,
+            static_ttl_secs: 30 * 60,
+            max_entries: 100,
+        }
+    }
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            page_size: 500,
+            concurrency: 4,
+            max_running_jobs: 2,
+            permissions_concurrency: 8,
+            default_directory: None,
+            format: ExportFormat::Csv,
+        }
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            level: LogLevel::Info,
+            max_size_mb: 10,
+            max_files: 10,
+            max_age_days: 30,
+            compress: true,
+            format: LogFormat::Text,
+            redaction: RedactionSettings::default(),
+        }
+    }
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        RedactionSettings {
+            emails: true,
+            secrets: true,
+            user_names: true,
+            node_names: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl From<LogLevel> for tracing::level_filters::LevelFilter {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
+}
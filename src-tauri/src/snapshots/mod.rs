@@ -0,0 +1,269 @@
+use chrono::{Duration, Utc};
+use dco3::eventlog::EventlogParams;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{async_runtime::spawn_blocking, State};
+
+use crate::{
+    error::AppError,
+    events::get_all_events,
+    groups::{get_all_group_users, get_all_groups},
+    guard::{require_roles, GuardedCommand},
+    models::{ListParams, Range},
+    pagination::Paginator,
+    permissions::get_all_permissions,
+    users::get_all_users,
+    AppState,
+};
+use models::{
+    SerializedSnapshot, SerializedSnapshotEventList, SerializedSnapshotList, SnapshotKind,
+    SnapshotParams,
+};
+use query::{filter_items, page_items, reject_sort, DEFAULT_LIMIT};
+use store::SnapshotItems;
+
+pub use store::SnapshotStore;
+
+mod models;
+mod query;
+mod store;
+
+const DEFAULT_EVENTS_DAYS: u32 = 7;
+
+/// Skips parts of a snapshot the connected user has no role for
+async fn is_allowed(state: &AppState, command: GuardedCommand) -> Result<bool, AppError> {
+    match require_roles(state, command).await {
+        Ok(()) => Ok(true),
+        Err(AppError::MissingRole(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn to_values<T: Serialize>(items: Vec<T>) -> Result<Vec<Value>, AppError> {
+    items
+        .into_iter()
+        .map(|item| serde_json::to_value(item).map_err(AppError::from))
+        .collect()
+}
+
+/// Runs a blocking snapshot store operation off the async runtime
+async fn with_store<T, F>(state: &AppState, f: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&SnapshotStore) -> Result<T, AppError> + Send + 'static,
+{
+    let store = state.snapshots();
+    spawn_blocking(move || f(&store))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn take_snapshot(
+    params: Option<SnapshotParams>,
+    state: State<'_, AppState>,
+) -> Result<SerializedSnapshot, AppError> {
+    let timer = state.timer("take_snapshot");
+    let params = params.unwrap_or_default();
+    let profile = state.active_profile().await?;
+    let paginator: Paginator = (&state.settings().await.export).into();
+    let mut items = Vec::new();
+
+    if is_allowed(&state, GuardedCommand::GetUsers).await? {
+        let users = get_all_users(&state, &profile, ListParams::default(), paginator).await?;
+        items.push(SnapshotItems {
+            kind: SnapshotKind::Users,
+            parent_id: None,
+            items: to_values(users)?,
+        });
+
+        let groups = get_all_groups(&state, &profile, ListParams::default(), paginator).await?;
+
+        for group in &groups {
+            let group_users =
+                get_all_group_users(&state, &profile, group.id, ListParams::default(), paginator)
+                    .await?;
+            items.push(SnapshotItems {
+                kind: SnapshotKind::GroupUsers,
+                parent_id: Some(group.id as i64),
+                items: to_values(group_users)?,
+            });
+        }
+
+        items.push(SnapshotItems {
+            kind: SnapshotKind::Groups,
+            parent_id: None,
+            items: to_values(groups)?,
+        });
+    }
+
+    if is_allowed(&state, GuardedCommand::GetPermissions).await? {
+        let permissions = get_all_permissions(&state, &profile).await?;
+        items.push(SnapshotItems {
+            kind: SnapshotKind::Permissions,
+            parent_id: None,
+            items: to_values(permissions)?,
+        });
+    }
+
+    if is_allowed(&state, GuardedCommand::GetEvents).await? {
+        let days = params.events_days.unwrap_or(DEFAULT_EVENTS_DAYS);
+        let events_params = EventlogParams {
+            offset: None,
+            limit: None,
+            user_id: None,
+            operation_type: None,
+            date_start: Some(Utc::now() - Duration::days(days as i64)),
+            date_end: None,
+            status: None,
+            user_client: None,
+            sort: None,
+        };
+        let events = get_all_events(&state, &profile, events_params, paginator).await?;
+        items.push(SnapshotItems {
+            kind: SnapshotKind::Events,
+            parent_id: None,
+            items: to_values(events)?,
+        });
+    }
+
+    let name = profile.name().to_string();
+    let url = profile.url().to_string();
+    let created_at = Utc::now().to_rfc3339();
+
+    let snapshot = with_store(&state, move |store| {
+        let id = store.insert(&name, &url, &created_at, &items)?;
+        store
+            .list()?
+            .into_iter()
+            .find(|snapshot| snapshot.id == id)
+            .ok_or_else(|| AppError::Storage(format!("Snapshot {id} not found after insert")))
+    })
+    .await
+    .map_err(|e| e.logged("Error storing snapshot"))?;
+
+    let elapsed = timer.elapsed().as_millis();
+    tracing::info!("Took snapshot {} in {elapsed} ms", snapshot.id);
+    timer.finish();
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub async fn list_snapshots(
+    state: State<'_, AppState>,
+) -> Result<Vec<SerializedSnapshot>, AppError> {
+    with_store(&state, |store| store.list()).await
+}
+
+#[tauri::command]
+pub async fn delete_snapshot(snapshot_id: i64, state: State<'_, AppState>) -> Result<(), AppError> {
+    with_store(&state, move |store| store.delete(snapshot_id)).await?;
+    tracing::info!("Deleted snapshot {snapshot_id}");
+    Ok(())
+}
+
+async fn get_snapshot_items(
+    state: &AppState,
+    snapshot_id: i64,
+    kind: SnapshotKind,
+    parent_id: Option<i64>,
+) -> Result<Vec<Value>, AppError> {
+    with_store(state, move |store| {
+        store.items(snapshot_id, kind, parent_id)
+    })
+    .await
+}
+
+/// Pages in SQL - only filtered lists are read completely and paged in memory
+async fn get_snapshot_page(
+    state: &AppState,
+    snapshot_id: i64,
+    kind: SnapshotKind,
+    parent_id: Option<i64>,
+    params: &ListParams,
+) -> Result<SerializedSnapshotList, AppError> {
+    if params.filter.is_some() {
+        let items = get_snapshot_items(state, snapshot_id, kind, parent_id).await?;
+        return page_items(items, params);
+    }
+
+    reject_sort(params)?;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let (items, total) = with_store(state, move |store| {
+        store.page(snapshot_id, kind, parent_id, offset, limit)
+    })
+    .await?;
+
+    Ok(SerializedSnapshotList {
+        range: Range {
+            offset,
+            limit,
+            total,
+        },
+        items,
+    })
+}
+
+#[tauri::command]
+pub async fn get_snapshot_users(
+    snapshot_id: i64,
+    params: ListParams,
+    state: State<'_, AppState>,
+) -> Result<SerializedSnapshotList, AppError> {
+    get_snapshot_page(&state, snapshot_id, SnapshotKind::Users, None, &params).await
+}
+
+#[tauri::command]
+pub async fn get_snapshot_groups(
+    snapshot_id: i64,
+    params: ListParams,
+    state: State<'_, AppState>,
+) -> Result<SerializedSnapshotList, AppError> {
+    get_snapshot_page(&state, snapshot_id, SnapshotKind::Groups, None, &params).await
+}
+
+#[tauri::command]
+pub async fn get_snapshot_group_users(
+    snapshot_id: i64,
+    group_id: u64,
+    params: ListParams,
+    state: State<'_, AppState>,
+) -> Result<SerializedSnapshotList, AppError> {
+    get_snapshot_page(
+        &state,
+        snapshot_id,
+        SnapshotKind::GroupUsers,
+        Some(group_id as i64),
+        &params,
+    )
+    .await
+}
+
+/// Permissions are not paged - same as `get_permissions`
+#[tauri::command]
+pub async fn get_snapshot_permissions(
+    snapshot_id: i64,
+    params: ListParams,
+    state: State<'_, AppState>,
+) -> Result<Vec<Value>, AppError> {
+    reject_sort(&params)?;
+    let permissions =
+        get_snapshot_items(&state, snapshot_id, SnapshotKind::Permissions, None).await?;
+    filter_items(permissions, params.filter.as_deref())
+}
+
+#[tauri::command]
+pub async fn get_snapshot_events(
+    snapshot_id: i64,
+    params: ListParams,
+    state: State<'_, AppState>,
+) -> Result<SerializedSnapshotEventList, AppError> {
+    let SerializedSnapshotList { range, items } =
+        get_snapshot_page(&state, snapshot_id, SnapshotKind::Events, None, &params).await?;
+
+    Ok(SerializedSnapshotEventList {
+        range,
+        events: items,
+    })
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::Range;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnapshotKind {
+    Users,
+    Groups,
+    GroupUsers,
+    Permissions,
+    Events,
+}
+
+impl SnapshotKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotKind::Users => "users",
+            SnapshotKind::Groups => "groups",
+            SnapshotKind::GroupUsers => "groupUsers",
+            SnapshotKind::Permissions => "permissions",
+            SnapshotKind::Events => "events",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotParams {
+    /// events of the last days to include (default: 7)
+    pub events_days: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSnapshot {
+    pub id: i64,
+    pub profile: String,
+    pub url: String,
+    pub created_at: String,
+    // item counts per kind
+    pub users: u64,
+    pub groups: u64,
+    pub group_users: u64,
+    pub permissions: u64,
+    pub events: u64,
+}
+
+/// Same shape as the lists returned by the online commands
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSnapshotList {
+    pub range: Range,
+    pub items: Vec<Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedSnapshotEventList {
+    pub range: Range,
+    pub events: Vec<Value>,
+}
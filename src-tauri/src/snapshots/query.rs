@@ -0,0 +1,219 @@
+use serde_json::Value;
+
+use crate::{
+    error::AppError,
+    filter::{parse_filter, FilterExpression, FilterOperator},
+    models::ListParams,
+};
+
+use super::models::SerializedSnapshotList;
+use crate::models::Range;
+
+/// Same default page size as the DRACOON API
+pub const DEFAULT_LIMIT: u64 = 500;
+
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.to_lowercase()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Values of a field - nested lists (e.g. user permissions) are searched as well
+fn field_values(item: &Value, field: &str) -> Vec<String> {
+    // roles are nested in role lists (`userRoles.items[].name`)
+    if field == "hasRole" {
+        return ["userRoles", "groupRoles"]
+            .iter()
+            .filter_map(|key| item.get(key)?.get("items")?.as_array())
+            .flatten()
+            .filter_map(|role| role.get("name").and_then(value_to_string))
+            .collect();
+    }
+
+    if let Some(value) = item.get(field).and_then(value_to_string) {
+        return vec![value];
+    }
+
+    item.as_object()
+        .map(|object| {
+            object
+                .values()
+                .filter_map(|value| value.as_array())
+                .flatten()
+                .filter_map(|entry| entry.get(field).and_then(value_to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn compare(actual: &str, expected: &str) -> std::cmp::Ordering {
+    match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(actual), Ok(expected)) => actual
+            .partial_cmp(&expected)
+            .unwrap_or(std::cmp::Ordering::Equal),
+        _ => actual.cmp(expected),
+    }
+}
+
+fn matches(item: &Value, expression: &FilterExpression) -> bool {
+    let values = field_values(item, &expression.field);
+    // snapshot values are compared case-insensitively
+    let expected = expression.value.to_lowercase();
+    let expected = expected.as_str();
+
+    match expression.operator {
+        FilterOperator::Eq => values.iter().any(|value| value == expected),
+        FilterOperator::Neq => values.iter().all(|value| value != expected),
+        FilterOperator::Cn => values.iter().any(|value| value.contains(expected)),
+        FilterOperator::Ge => values.iter().any(|value| compare(value, expected).is_ge()),
+        FilterOperator::Le => values.iter().any(|value| compare(value, expected).is_le()),
+    }
+}
+
+/// Applies filter and paging of the params to snapshot items
+pub fn filter_items(items: Vec<Value>, filter: Option<&str>) -> Result<Vec<Value>, AppError> {
+    let expressions = match filter {
+        Some(filter) => parse_filter(filter)?,
+        None => return Ok(items),
+    };
+
+    Ok(items
+        .into_iter()
+        .filter(|item| {
+            expressions
+                .iter()
+                .all(|expression| matches(item, expression))
+        })
+        .collect())
+}
+
+/// Snapshot items keep the order they were fetched in
+pub fn reject_sort(params: &ListParams) -> Result<(), AppError> {
+    match params.sort {
+        Some(_) => Err(AppError::InvalidInput(
+            "Sorting is not supported for snapshots".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Same paging as the DRACOON API (offset, limit - default 500)
+pub fn page_items(
+    items: Vec<Value>,
+    params: &ListParams,
+) -> Result<SerializedSnapshotList, AppError> {
+    reject_sort(params)?;
+    let items = filter_items(items, params.filter.as_deref())?;
+    let total = items.len() as u64;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+
+    let items = items
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect();
+
+    Ok(SerializedSnapshotList {
+        range: Range {
+            offset,
+            limit,
+            total,
+        },
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn users() -> Vec<Value> {
+        vec![
+            json!({
+                "id": 1,
+                "userName": "jane.doe",
+                "isLocked": false,
+                "expireAt": "2024-01-01T10:00:00Z",
+                "userRoles": { "items": [{ "name": "USER_MANAGER" }] }
+            }),
+            json!({
+                "id": 2,
+                "userName": "John.Smith",
+                "isLocked": true,
+                "expireAt": "2025-06-01T10:00:00Z",
+                "userRoles": { "items": [] }
+            }),
+        ]
+    }
+
+    fn ids(items: &[Value]) -> Vec<u64> {
+        items
+            .iter()
+            .filter_map(|item| item["id"].as_u64())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_is_case_insensitive() {
+        let items = filter_items(users(), Some("userName:cn:SMITH")).unwrap();
+        assert_eq!(ids(&items), vec![2]);
+    }
+
+    #[test]
+    fn test_filter_combines_expressions() {
+        let items = filter_items(users(), Some("isLocked:eq:false|id:ge:1")).unwrap();
+        assert_eq!(ids(&items), vec![1]);
+
+        let items = filter_items(users(), Some("id:neq:1")).unwrap();
+        assert_eq!(ids(&items), vec![2]);
+    }
+
+    #[test]
+    fn test_filter_value_with_colons() {
+        let items = filter_items(users(), Some("expireAt:le:2024-01-01T10:00:00Z")).unwrap();
+        assert_eq!(ids(&items), vec![1]);
+    }
+
+    #[test]
+    fn test_filter_nested_roles() {
+        let items = filter_items(users(), Some("hasRole:eq:user_manager")).unwrap();
+        assert_eq!(ids(&items), vec![1]);
+    }
+
+    #[test]
+    fn test_invalid_filter() {
+        assert!(filter_items(users(), Some("userName:like:doe")).is_err());
+    }
+
+    #[test]
+    fn test_page_items() {
+        let params = ListParams {
+            offset: Some(1),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let page = page_items(users(), &params).unwrap();
+
+        assert_eq!(page.range.total, 2);
+        assert_eq!(ids(&page.items), vec![2]);
+    }
+
+    #[test]
+    fn test_sort_is_rejected() {
+        let params = ListParams {
+            sort: Some("userName:asc".to_string()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            page_items(users(), &params),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+use super::models::{SerializedSnapshot, SnapshotKind};
+
+pub const SNAPSHOT_DB: &str = "snapshots.db";
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        profile TEXT NOT NULL,
+        url TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshot_items (
+        snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        parent_id INTEGER,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshot_items_kind
+        ON snapshot_items (snapshot_id, kind, parent_id, position);
+";
+
+/// Items of one kind fetched for a snapshot - group users are stored per group
+pub struct SnapshotItems {
+    pub kind: SnapshotKind,
+    pub parent_id: Option<i64>,
+    pub items: Vec<Value>,
+}
+
+/// SQLite database in the config directory - opened on first use
+pub struct SnapshotStore {
+    path: PathBuf,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SnapshotStore {
+    pub fn new(config_dir: &Path) -> Self {
+        SnapshotStore {
+            path: config_dir.join(SNAPSHOT_DB),
+            connection: Mutex::new(None),
+        }
+    }
+
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| AppError::Storage("Snapshot store lock poisoned".to_string()))?;
+
+        if connection.is_none() {
+            let opened = Connection::open(&self.path)?;
+            opened.execute_batch(SCHEMA)?;
+            *connection = Some(opened);
+        }
+
+        match connection.as_mut() {
+            Some(connection) => f(connection),
+            None => Err(AppError::Storage(
+                "Snapshot store not available".to_string(),
+            )),
+        }
+    }
+
+    /// Stores all items of a snapshot in one transaction
+    pub fn insert(
+        &self,
+        profile: &str,
+        url: &str,
+        created_at: &str,
+        items: &[SnapshotItems],
+    ) -> Result<i64, AppError> {
+        self.with_connection(|connection| {
+            let tx = connection.transaction()?;
+
+            tx.execute(
+                "INSERT INTO snapshots (profile, url, created_at) VALUES (?1, ?2, ?3)",
+                params![profile, url, created_at],
+            )?;
+            let snapshot_id = tx.last_insert_rowid();
+
+            {
+                let mut statement = tx.prepare(
+                    "INSERT INTO snapshot_items (snapshot_id, kind, parent_id, position, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+
+                for entry in items {
+                    for (position, item) in entry.items.iter().enumerate() {
+                        statement.execute(params![
+                            snapshot_id,
+                            entry.kind.as_str(),
+                            entry.parent_id,
+                            position as i64,
+                            item.to_string()
+                        ])?;
+                    }
+                }
+            }
+
+            tx.commit()?;
+            Ok(snapshot_id)
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<SerializedSnapshot>, AppError> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare(
+                "SELECT s.id, s.profile, s.url, s.created_at,
+                    SUM(i.kind = 'users'), SUM(i.kind = 'groups'), SUM(i.kind = 'groupUsers'),
+                    SUM(i.kind = 'permissions'), SUM(i.kind = 'events')
+                 FROM snapshots s LEFT JOIN snapshot_items i ON i.snapshot_id = s.id
+                 GROUP BY s.id ORDER BY s.created_at DESC",
+            )?;
+
+            let snapshots = statement
+                .query_map([], |row| {
+                    Ok(SerializedSnapshot {
+                        id: row.get(0)?,
+                        profile: row.get(1)?,
+                        url: row.get(2)?,
+                        created_at: row.get(3)?,
+                        users: row.get::<_, Option<u64>>(4)?.unwrap_or(0),
+                        groups: row.get::<_, Option<u64>>(5)?.unwrap_or(0),
+                        group_users: row.get::<_, Option<u64>>(6)?.unwrap_or(0),
+                        permissions: row.get::<_, Option<u64>>(7)?.unwrap_or(0),
+                        events: row.get::<_, Option<u64>>(8)?.unwrap_or(0),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(snapshots)
+        })
+    }
+
+    pub fn delete(&self, snapshot_id: i64) -> Result<(), AppError> {
+        self.with_connection(|connection| {
+            let deleted =
+                connection.execute("DELETE FROM snapshots WHERE id = ?1", [snapshot_id])?;
+
+            if deleted == 0 {
+                return Err(AppError::InvalidInput(format!(
+                    "Snapshot {snapshot_id} does not exist"
+                )));
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Items of a kind in the order they were fetched
+    pub fn items(
+        &self,
+        snapshot_id: i64,
+        kind: SnapshotKind,
+        parent_id: Option<i64>,
+    ) -> Result<Vec<Value>, AppError> {
+        self.with_connection(|connection| {
+            ensure_exists(connection, snapshot_id)?;
+
+            let mut statement = connection.prepare(
+                "SELECT data FROM snapshot_items
+                 WHERE snapshot_id = ?1 AND kind = ?2 AND (?3 IS NULL OR parent_id = ?3)
+                 ORDER BY parent_id, position",
+            )?;
+
+            let rows = statement
+                .query_map(params![snapshot_id, kind.as_str(), parent_id], |row| {
+                    row.get::<_, String>(0)
+                })?;
+
+            parse_items(rows)
+        })
+    }
+
+    /// One page of items of a kind and the total count - only the page is read
+    pub fn page(
+        &self,
+        snapshot_id: i64,
+        kind: SnapshotKind,
+        parent_id: Option<i64>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(Vec<Value>, u64), AppError> {
+        self.with_connection(|connection| {
+            ensure_exists(connection, snapshot_id)?;
+
+            let total = connection.query_row(
+                "SELECT COUNT(*) FROM snapshot_items
+                 WHERE snapshot_id = ?1 AND kind = ?2 AND (?3 IS NULL OR parent_id = ?3)",
+                params![snapshot_id, kind.as_str(), parent_id],
+                |row| row.get::<_, u64>(0),
+            )?;
+
+            let mut statement = connection.prepare(
+                "SELECT data FROM snapshot_items
+                 WHERE snapshot_id = ?1 AND kind = ?2 AND (?3 IS NULL OR parent_id = ?3)
+                 ORDER BY parent_id, position
+                 LIMIT ?4 OFFSET ?5",
+            )?;
+
+            // SQLite takes signed limits - larger values are all rows anyway
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            let offset = i64::try_from(offset).unwrap_or(i64::MAX);
+            let rows = statement.query_map(
+                params![snapshot_id, kind.as_str(), parent_id, limit, offset],
+                |row| row.get::<_, String>(0),
+            )?;
+
+            Ok((parse_items(rows)?, total))
+        })
+    }
+}
+
+fn ensure_exists(connection: &Connection, snapshot_id: i64) -> Result<(), AppError> {
+    let exists = connection
+        .query_row(
+            "SELECT id FROM snapshots WHERE id = ?1",
+            [snapshot_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?;
+
+    match exists {
+        Some(_) => Ok(()),
+        None => Err(AppError::InvalidInput(format!(
+            "Snapshot {snapshot_id} does not exist"
+        ))),
+    }
+}
+
+fn parse_items(
+    rows: impl Iterator<Item = rusqlite::Result<String>>,
+) -> Result<Vec<Value>, AppError> {
+    rows.map(|data| {
+        let data = data?;
+        serde_json::from_str(&data).map_err(AppError::from)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_page_reads_requested_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(dir.path());
+        let users = SnapshotItems {
+            kind: SnapshotKind::Users,
+            parent_id: None,
+            items: (1..=3).map(|id| json!({ "id": id })).collect(),
+        };
+        let snapshot_id = store
+            .insert(
+                "profile",
+                "https://dracoon.team",
+                "2024-01-01T00:00:00Z",
+                &[users],
+            )
+            .unwrap();
+
+        let (items, total) = store
+            .page(snapshot_id, SnapshotKind::Users, None, 1, 1)
+            .unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(items, vec![json!({ "id": 2 })]);
+
+        let (items, total) = store
+            .page(snapshot_id, SnapshotKind::Groups, None, 0, 500)
+            .unwrap();
+        assert_eq!(total, 0);
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn test_page_of_missing_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(dir.path());
+
+        assert!(matches!(
+            store.page(1, SnapshotKind::Users, None, 0, 500),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
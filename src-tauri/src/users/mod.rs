@@ -1,11 +1,20 @@
-use std::time::Instant;
-
-use crate::{config::log_dracoon_error, models::ListParams, AppState};
-use dco3::Users;
-use models::{FlattenedUserItem, SerializedUserItem, SerializedUserList};
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    cache::CachePolicy,
+    error::AppError,
+    guard::{require_roles, GuardedCommand},
+    jobs::{enqueue_export, ExportRequest, Job},
+    models::{ListParams, Profile},
+    pagination::{Page, Paginator},
+    AppState,
+};
+use dco3::{ListAllParams, Users};
+use models::FlattenedUserItem;
 use tauri::State;
 
-pub (crate) use models::SerializedRoleList;
+pub (crate) use models::{SerializedRoleList, SerializedUserItem};
+pub use models::{SerializedUserList, UsersCacheKey};
 
 mod models;
 
@@ -13,25 +22,21 @@ mod models;
 pub async fn get_users(
     state: State<'_, AppState>,
     params: ListParams,
-) -> Result<SerializedUserList, String> {
-    let now = Instant::now();
-    let client = state.get_client().await?;
-    let params = params.try_into()?;
+    force_refresh: Option<bool>,
+) -> Result<SerializedUserList, AppError> {
+    require_roles(&state, GuardedCommand::GetUsers).await?;
+    let timer = state.timer("get_users");
 
-    let users = client
-        .users()
-        .get_users(Some(params), Some(true), None)
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching users"));
-            e.to_string()
-        })?;
+    let profile = state.active_profile().await?;
+    let cache = CachePolicy::from_force_refresh(force_refresh);
+    let users = get_users_page(&state, &profile, params, cache).await?;
 
     let user_count = users.items.len();
 
-    let elapsed = now.elapsed().as_millis();
+    let elapsed = timer.elapsed().as_millis();
     tracing::info!("Fetched {user_count} users in {elapsed} ms");
-    Ok(users.into())
+    timer.finish();
+    Ok((*users).clone())
 }
 
 #[tauri::command]
@@ -39,73 +44,130 @@ pub async fn export_users(
     params: ListParams,
     path: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<String, AppError> {
+    require_roles(&state, GuardedCommand::ExportUsers).await?;
+
+    enqueue_export(&state, ExportRequest::Users(params), path).await
+}
+
+/// Writes all users matching the params page by page - returns the number of written rows
+pub(crate) async fn write_users(
+    state: &AppState,
+    params: ListParams,
+    job: &mut Job,
+) -> Result<u64, AppError> {
     let now = Instant::now();
-    let client = state.get_client().await?;
+    let export = state.settings().await.export;
+    let profile = job.profile();
 
-    let mut users = client
-        .users()
-        .get_users(Some(params.clone().try_into()?), Some(true), None)
-        .await
-        .map_err(|e| {
-            log_dracoon_error(&e, Some("Error fetching users"));
-            e.to_string()
-        })?;
-
-    let elapsed_first_500_users = now.elapsed().as_millis();
-    tracing::info!("Fetched first 500 users in {elapsed_first_500_users} ms");
-
-    for offset in (500..users.range.total).step_by(500) {
-        tracing::debug!("Fetching users with offset {offset}");
-        let params = ListParams {
-            offset: Some(offset),
-            ..params.clone()
-        };
-
-        let new_users = client
-            .users()
-            .get_users(Some(params.try_into()?), Some(true), None)
-            .await
-            .map_err(|e| {
-                log_dracoon_error(&e, Some("Error fetching users"));
-                e.to_string()
-            })?;
+    let mut writer = job.writer(export.format)?;
+    let mut offset = job.resume_offset();
+    let paginator = Paginator::from(&export).starting_at(offset);
+
+    for_each_users_page(state, &profile, params, paginator, |page| {
+        offset += page.items.len() as u64;
+        writer.write_items(page.items.into_iter().map(FlattenedUserItem::from))?;
+        job.page_written(&mut writer, offset, page.total)
+    })
+    .await?;
+
+    let user_count = writer.finish()?;
+
+    let elapsed_export = now.elapsed().as_millis();
+    tracing::info!("Exported {user_count} users in {elapsed_export} ms");
+
+    Ok(user_count)
+}
 
-        users.items.extend(new_users.items);
+/// Fetches a page of users - pages are cached per url and params
+async fn get_users_page(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    cache: CachePolicy,
+) -> Result<Arc<SerializedUserList>, AppError> {
+    let client = profile.get_client().await?;
+    let key = UsersCacheKey::new(client.get_base_url().to_string(), params.clone());
+
+    let cached = if cache.reads() {
+        profile.get_users_cache().await.get(&key).await
+    } else {
+        None
+    };
+
+    if let Some(users) = cached {
+        tracing::debug!("Using cached users (offset {:?})", params.offset);
+        return Ok(users);
     }
 
-    let elapsed_all_users = now.elapsed().as_millis();
-    tracing::info!("Fetched all users in {elapsed_all_users} ms");
-
-    let serialized_users: Vec<SerializedUserItem> = users
-        .items
-        .into_iter()
-        .map(|user| user.into())
-        .collect::<Vec<_>>();
-    let flattened_users: Vec<FlattenedUserItem> = serialized_users
-        .into_iter()
-        .map(|user| user.into())
-        .collect::<Vec<_>>();
-
-    let mut csv_writer = csv::Writer::from_path(path).map_err(|e| {
-        tracing::error!("Error creating CSV writer: {e}");
-        e.to_string()
-    })?;
-
-    for user in flattened_users {
-        csv_writer.serialize(user).map_err(|e| {
-            tracing::error!("Error serializing user info: {e}");
-            e.to_string()
-        })?;
+    let params: ListAllParams = params.try_into()?;
+
+    let users = state
+        .call_with(profile, "Error fetching users", |client| {
+            let params = params.clone();
+            async move { client.users().get_users(Some(params), Some(true), None).await }
+        })
+        .await?;
+
+    let users: Arc<SerializedUserList> = Arc::new(users.into());
+
+    if cache.writes() {
+        profile
+            .get_users_cache()
+            .await
+            .insert(key, users.clone())
+            .await;
     }
 
-    csv_writer.flush().map_err(|e| {
-        tracing::error!("Error flushing CSV writer: {e}");
-        e.to_string()
-    })?;
+    Ok(users)
+}
+
+/// Fetches all pages of users matching the params and passes them on in order.
+/// Cached pages are reused, fetched pages are not cached - a bulk fetch would evict the pages cached while browsing.
+pub(crate) async fn for_each_users_page<C>(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    paginator: Paginator,
+    on_page: C,
+) -> Result<u64, AppError>
+where
+    C: FnMut(Page<SerializedUserItem>) -> Result<(), AppError>,
+{
+    paginator
+        .for_each_page(
+            |offset, limit| {
+                let params = ListParams {
+                    offset: Some(offset),
+                    limit: Some(limit),
+                    ..params.clone()
+                };
+
+                async move {
+                    let users =
+                        get_users_page(state, profile, params, CachePolicy::ReadOnly).await?;
+                    Ok(Page::new(users.range.total, users.items.clone()))
+                }
+            },
+            on_page,
+        )
+        .await
+}
+
+/// Fetches all pages of users matching the params
+pub(crate) async fn get_all_users(
+    state: &AppState,
+    profile: &Profile,
+    params: ListParams,
+    paginator: Paginator,
+) -> Result<Vec<SerializedUserItem>, AppError> {
+    let mut users = Vec::new();
 
-    let elapsed_csv = now.elapsed().as_millis();
-    tracing::info!("Exported all users to CSV in {elapsed_csv} ms");
+    for_each_users_page(state, profile, params, paginator, |page| {
+        users.extend(page.items);
+        Ok(())
+    })
+    .await?;
 
-    Ok(())
+    Ok(users)
 }
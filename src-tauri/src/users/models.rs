@@ -1,19 +1,55 @@
+use std::hash::Hash;
+
 use dco3::{
     roles::{Role, RoleList},
     users::{UserItem, UserList},
 };
 use serde::Serialize;
 
-use crate::{models::Range, ROLE_AUDITOR, ROLE_CONFIG_MANAGER, ROLE_GROUP_MANAGER, ROLE_GUEST_USER, ROLE_ROOM_MANAGER, ROLE_USER_MANAGER};
+use crate::{cache::CacheKey, models::{ListParams, Range}, ROLE_AUDITOR, ROLE_CONFIG_MANAGER, ROLE_GROUP_MANAGER, ROLE_GUEST_USER, ROLE_ROOM_MANAGER, ROLE_USER_MANAGER};
 
-#[derive(Serialize)]
+#[derive(PartialEq, Eq)]
+pub struct UsersCacheKey {
+    url: String,
+    params: ListParams
+}
+
+impl UsersCacheKey {
+    pub fn new(url: String, params: ListParams) -> Self {
+        Self {
+            url,
+            params
+        }
+    }
+}
+
+impl From<&UsersCacheKey> for String {
+    fn from(value: &UsersCacheKey) -> Self {
+        format!("{}{}", value.url, value.params.to_string())
+    }
+}
+
+impl CacheKey for UsersCacheKey {
+    fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Hash for UsersCacheKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let key: String = self.into();
+        key.hash(state);
+    }
+}
+
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedUserList {
     pub range: Range,
     pub items: Vec<SerializedUserItem>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedUserItem {
     pub id: u64,
@@ -87,13 +123,13 @@ impl From<SerializedUserItem> for FlattenedUserItem {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedRoleList {
     pub items: Vec<SerializedRoleItem>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedRoleItem {
     pub id: u64,
@@ -1,9 +1,11 @@
 import { invoke } from "@tauri-apps/api";
-import type { UserAccount } from "./models";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { AppError } from "$lib/models";
+import type { CredentialBackend, CredentialStore, SessionExpired, UserAccount } from "./models";
 
-export const initAuthCodeFlow = async (url: string): Promise<boolean> => {
+export const initAuthCodeFlow = async (url: string, profile?: string): Promise<boolean> => {
     try {
-         const isRefreshToken: boolean = await invoke('init_auth_code_flow', { url });
+         const isRefreshToken: boolean = await invoke('init_auth_code_flow', { url, profile });
          return isRefreshToken; 
     }
     catch (error) {
@@ -25,4 +27,62 @@ export const connect = async (isRefreshToken: boolean, authCode?: string): Promi
         console.error(error);
         throw error;
     }
-}
\ No newline at end of file
+}
+
+export const logout = async (): Promise<void> => {
+    try {
+        await invoke('logout');
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const onLoopbackLogin = async (
+    onConnected: (account: UserAccount) => void,
+    onFailed: (error: AppError) => void
+): Promise<UnlistenFn> => {
+    const unlistenConnected = await listen<UserAccount>('auth-connected', (event) => onConnected(event.payload));
+    const unlistenFailed = await listen<AppError>('auth-failed', (event) => onFailed(event.payload));
+
+    return () => {
+        unlistenConnected();
+        unlistenFailed();
+    };
+}
+
+export const onSessionExpired = async (callback: (session: SessionExpired) => void): Promise<UnlistenFn> => {
+    return listen<SessionExpired>('session-expired', (event) => callback(event.payload));
+}
+
+export const getCredentialStore = async (): Promise<CredentialStore> => {
+    try {
+        let store: CredentialStore = await invoke('get_credential_store');
+        return store;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const setCredentialBackend = async (backend: CredentialBackend): Promise<void> => {
+    try {
+        await invoke('set_credential_backend', { backend });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const unlockCredentialStore = async (passphrase: string): Promise<void> => {
+    try {
+        await invoke('unlock_credential_store', { passphrase });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
@@ -21,4 +21,19 @@ export interface UserAccount {
     email: string;
     userId: number;
     isCloud: boolean;
-}
\ No newline at end of file
+}
+
+export interface SessionExpired {
+    profile: string;
+    url: string;
+}
+
+export type CredentialBackend = 'auto' | 'keyring' | 'file';
+
+export interface CredentialStore {
+    backend: CredentialBackend;
+    resolvedBackend: CredentialBackend;
+    keyringAvailable: boolean;
+    isUnlocked: boolean;
+    fileStoreExists: boolean;
+}
@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api";
+import type { InvalidateCacheParams, ProfileCacheStats } from "./models";
+
+export const getCacheStats = async (): Promise<ProfileCacheStats[]> => {
+    try {
+        let stats: ProfileCacheStats[] = await invoke('get_cache_stats');
+        return stats;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const invalidateCache = async (params?: InvalidateCacheParams): Promise<number> => {
+    try {
+        let invalidated: number = await invoke('invalidate_cache', { params });
+        return invalidated;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
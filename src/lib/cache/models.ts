@@ -0,0 +1,29 @@
+export type CacheName =
+    | 'permissions'
+    | 'customer'
+    | 'events'
+    | 'operations'
+    | 'users'
+    | 'groups'
+    | 'group'
+    | 'groupUsers';
+
+export interface CacheStats {
+    name: CacheName;
+    entries: number;
+    hits: number;
+    misses: number;
+    hitRate: number;
+    oldestEntryAgeSecs?: number;
+}
+
+export interface ProfileCacheStats {
+    profile: string;
+    url: string;
+    caches: CacheStats[];
+}
+
+export interface InvalidateCacheParams {
+    cache?: CacheName;
+    url?: string;
+}
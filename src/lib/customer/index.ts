@@ -1,9 +1,9 @@
 import { invoke } from "@tauri-apps/api";
 import type { CustomerInfo } from "./models";
 
-export const getCustomerInfo = async (): Promise<CustomerInfo> => {
+export const getCustomerInfo = async (forceRefresh = false): Promise<CustomerInfo> => {
     try {
-        let customer_info: CustomerInfo = await invoke('get_customer_info');
+        let customer_info: CustomerInfo = await invoke('get_customer_info', { forceRefresh });
         return customer_info;
     }
     catch (error) {
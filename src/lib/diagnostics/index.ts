@@ -0,0 +1,11 @@
+import { invoke } from "@tauri-apps/api";
+
+export const exportDiagnostics = async (path: string): Promise<void> => {
+    try {
+        await invoke('export_diagnostics', { path });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
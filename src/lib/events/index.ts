@@ -1,9 +1,10 @@
 import { invoke } from "@tauri-apps/api";
+import { runExport } from "$lib/jobs";
 import type { EventList, EventParams, OperationTypeList } from "./models";
 
-export const getEvents = async (params: EventParams): Promise<EventList> => {
+export const getEvents = async (params: EventParams, forceRefresh = false): Promise<EventList> => {
     try {
-        let events: EventList = await invoke('get_events', { params });
+        let events: EventList = await invoke('get_events', { params, forceRefresh });
         return events;
     }
     catch (error) {
@@ -14,7 +15,7 @@ export const getEvents = async (params: EventParams): Promise<EventList> => {
 
 export const downloadEvents = async (path: string, params: EventParams): Promise<void> => {
     try {
-        await invoke('export_events', { path, params });
+        await runExport('export_events', { path, params });
     }
     catch (error) {
         console.error(error);
@@ -22,9 +23,9 @@ export const downloadEvents = async (path: string, params: EventParams): Promise
     }
 }
 
-export const getOperationTypes = async (): Promise<OperationTypeList> => {
+export const getOperationTypes = async (forceRefresh = false): Promise<OperationTypeList> => {
     try {
-        let operationTypes: OperationTypeList = await invoke('get_operation_types');
+        let operationTypes: OperationTypeList = await invoke('get_operation_types', { forceRefresh });
         return operationTypes;
     }
     catch (error) {
@@ -1,10 +1,11 @@
 import type { ListParams } from "$lib/models";
 import { invoke } from "@tauri-apps/api";
+import { runExport } from "$lib/jobs";
 import type { GroupInfo, GroupList, GroupUserList } from "./models";
 
-export const getGroup = async (groupId: number): Promise<GroupInfo> => {
+export const getGroup = async (groupId: number, forceRefresh = false): Promise<GroupInfo> => {
     try {
-        let group: GroupInfo = await invoke('get_group', { groupId });
+        let group: GroupInfo = await invoke('get_group', { groupId, forceRefresh });
         return group;
     }
     catch (error) {
@@ -13,9 +14,9 @@ export const getGroup = async (groupId: number): Promise<GroupInfo> => {
     }
 }
 
-export const getGroups = async (params: ListParams): Promise<GroupList> => {
+export const getGroups = async (params: ListParams, forceRefresh = false): Promise<GroupList> => {
     try {
-        let groups: GroupList = await invoke('get_groups', { params });
+        let groups: GroupList = await invoke('get_groups', { params, forceRefresh });
 
         console.log(groups);
 
@@ -29,7 +30,7 @@ export const getGroups = async (params: ListParams): Promise<GroupList> => {
 
 export const downloadGroups = async (params: ListParams, path: string): Promise<void> => {
     try {
-        await invoke('export_groups', { params, path });
+        await runExport('export_groups', { params, path });
     }
     catch (error) {
         console.error(error);
@@ -37,9 +38,9 @@ export const downloadGroups = async (params: ListParams, path: string): Promise<
     }
 }
 
-export const getGroupUsers = async (groupId: number, params: ListParams): Promise<GroupUserList> => {
+export const getGroupUsers = async (groupId: number, params: ListParams, forceRefresh = false): Promise<GroupUserList> => {
     try {
-        let users: GroupUserList = await invoke('get_group_users', { groupId, params });
+        let users: GroupUserList = await invoke('get_group_users', { groupId, params, forceRefresh });
         return users;
     }
     catch (error) {
@@ -50,7 +51,7 @@ export const getGroupUsers = async (groupId: number, params: ListParams): Promis
 
 export const downloadGroupUsers = async (groupId: number, params: ListParams, path: string): Promise<void> => {
     try {
-        await invoke('export_group_users', { groupId, params, path });
+        await runExport('export_group_users', { groupId, params, path });
     }
     catch (error) {
         console.error(error);
@@ -60,7 +61,7 @@ export const downloadGroupUsers = async (groupId: number, params: ListParams, pa
 
 export const downloadAllGroupUsers = async (path: string): Promise<void> => {
     try {
-        await invoke('export_all_group_users', { path });
+        await runExport('export_all_group_users', { path });
     }
     catch (error) {
         console.error(error);
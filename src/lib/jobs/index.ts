@@ -0,0 +1,95 @@
+import { invoke } from "@tauri-apps/api";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { Job, JobProgress } from "./models";
+
+export const listJobs = async (): Promise<Job[]> => {
+    try {
+        let jobs: Job[] = await invoke('list_jobs');
+        return jobs;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getJob = async (jobId: string): Promise<Job> => {
+    try {
+        let job: Job = await invoke('get_job', { jobId });
+        return job;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const cancelJob = async (jobId: string): Promise<void> => {
+    try {
+        await invoke('cancel_job', { jobId });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+// continues a failed export from its checkpoint - returns the id of the new job
+export const resumeJob = async (jobId: string): Promise<string> => {
+    try {
+        let newJobId: string = await invoke('resume_job', { jobId });
+        return newJobId;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const onJobProgress = async (callback: (progress: JobProgress) => void): Promise<UnlistenFn> => {
+    return listen<JobProgress>('job-progress', (event) => callback(event.payload));
+}
+
+const isFinished = (job: Job) => job.status !== 'queued' && job.status !== 'running';
+
+// resolves once the job completed - rejects with the job error if it failed or was cancelled
+export const waitForJob = async (jobId: string): Promise<Job> => {
+    let unlisten: UnlistenFn | null = null;
+
+    try {
+        return await new Promise<Job>((resolve, reject) => {
+            const settle = (job: Job) => {
+                if (!isFinished(job)) {
+                    return;
+                }
+
+                if (job.status === 'completed') {
+                    resolve(job);
+                } else {
+                    reject(job.error ?? { code: 'JobFailed', message: 'Export failed' });
+                }
+            };
+
+            onJobProgress((progress) => {
+                if (progress.jobId === jobId && progress.status !== 'queued' && progress.status !== 'running') {
+                    getJob(jobId).then(settle, reject);
+                }
+            })
+                .then((unlistenFn) => {
+                    unlisten = unlistenFn;
+                    // the job might have finished before the listener was registered
+                    return getJob(jobId);
+                })
+                .then(settle, reject);
+        });
+    }
+    finally {
+        unlisten?.();
+    }
+}
+
+// queues an export and waits for it to finish
+export const runExport = async (command: string, args: Record<string, unknown>): Promise<Job> => {
+    const jobId: string = await invoke(command, args);
+    return waitForJob(jobId);
+}
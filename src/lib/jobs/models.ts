@@ -0,0 +1,57 @@
+import type { EventParams } from "$lib/events/models";
+import type { ListParams } from "$lib/models";
+
+export type JobKind =
+    | 'users'
+    | 'groups'
+    | 'groupUsers'
+    | 'allGroupUsers'
+    | 'events'
+    | 'userPermissions'
+    | 'allUserPermissions';
+
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export type ExportRequest =
+    | { kind: 'users'; params: ListParams }
+    | { kind: 'groups'; params: ListParams }
+    | { kind: 'groupUsers'; params: { groupId: number; params: ListParams } }
+    | { kind: 'allGroupUsers' }
+    | { kind: 'events'; params: EventParams }
+    | { kind: 'userPermissions'; params: { params: ListParams; forceRefresh: boolean } }
+    | { kind: 'allUserPermissions' };
+
+export interface JobError {
+    code: string;
+    message: string;
+}
+
+export interface Job {
+    id: string;
+    status: JobStatus;
+    request: ExportRequest;
+    path: string;
+    profile?: string;
+    rows?: number;
+    createdAt: string;
+    startedAt?: string;
+    finishedAt?: string;
+    durationMs?: number;
+    error?: JobError;
+    // failed job continued by this job
+    resumedFrom?: string;
+}
+
+export interface JobProgress {
+    jobId: string;
+    kind: JobKind;
+    status: JobStatus;
+    pagesFetched: number;
+    processed: number;
+    total?: number;
+    rowsWritten: number;
+    // rows of paged exports (users, groups, events)
+    totalRows?: number;
+    elapsedMs: number;
+    etaSecs?: number;
+}
@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api";
+import type { LogList, LogParams } from "./models";
+import type { LogLevel } from "$lib/settings/models";
+
+export const readLogs = async (params: LogParams): Promise<LogList> => {
+    try {
+        let logs: LogList = await invoke('read_logs', { params });
+        return logs;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const setLogLevel = async (level: LogLevel): Promise<void> => {
+    try {
+        await invoke('set_log_level', { level });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
@@ -0,0 +1,22 @@
+import type { Range } from "$lib/models";
+import type { LogLevel } from "$lib/settings/models";
+
+export interface LogParams {
+    offset?: number;
+    limit?: number;
+    level?: LogLevel;
+    search?: string;
+}
+
+export interface LogEntry {
+    timestamp: string;
+    level: string;
+    target: string;
+    message: string;
+    file: string;
+}
+
+export interface LogList {
+    range: Range;
+    items: LogEntry[];
+}
@@ -16,4 +16,11 @@ export enum ToastType {
     Error = 'variant-filled-error',
     Warning = 'variant-filled-warning',
     Info = 'variant-filled-primary'
-}
\ No newline at end of file
+}
+
+export interface AppError {
+    code: string;
+    status?: number;
+    message: string;
+    debugInfo?: string;
+}
@@ -0,0 +1,24 @@
+import { invoke } from "@tauri-apps/api";
+import type { NetworkSettings } from "./models";
+
+export const getNetworkSettings = async (): Promise<NetworkSettings> => {
+    try {
+        let settings: NetworkSettings = await invoke('get_network_settings');
+        return settings;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const updateNetworkSettings = async (settings: NetworkSettings): Promise<NetworkSettings> => {
+    try {
+        let updated: NetworkSettings = await invoke('update_network_settings', { settings });
+        return updated;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
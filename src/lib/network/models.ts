@@ -0,0 +1,10 @@
+export interface NetworkSettings {
+    timeoutSecs?: number;
+    retry: RetrySettings;
+}
+
+export interface RetrySettings {
+    maxAttempts: number;
+    baseDelayMs: number;
+    maxDelaySecs: number;
+}
@@ -1,10 +1,11 @@
 import type { ListParams } from "$lib/models";
 import { invoke } from "@tauri-apps/api";
+import { runExport } from "$lib/jobs";
 import type { NodePermissions, NodePermissionsListEntry } from "./models";
 
-export const getPermissions = async (params: ListParams) => {
+export const getPermissions = async (params: ListParams, forceRefresh = false) => {
     try {
-        let permissionsList: NodePermissionsListEntry[] = await invoke('get_permissions', { params });
+        let permissionsList: NodePermissionsListEntry[] = await invoke('get_permissions', { params, forceRefresh });
         return permissionsList.filter((entry) => entry.nodeCreatedById !== 0);
     }
     catch (error) {
@@ -13,9 +14,9 @@ export const getPermissions = async (params: ListParams) => {
     }
 }
 
-export const downloadUserPermissions = async (path: string, params: ListParams): Promise<void> => {
+export const downloadUserPermissions = async (path: string, params: ListParams, forceRefresh = false): Promise<void> => {
     try {
-        await invoke('export_user_permissions', { path, params });
+        await runExport('export_user_permissions', { path, params, forceRefresh });
     }
     catch (error) {
         console.error(error);
@@ -25,7 +26,7 @@ export const downloadUserPermissions = async (path: string, params: ListParams):
 
 export const downloadAllUserPermissions = async (path: string): Promise<void> => {
     try {
-        await invoke('export_all_user_permissions', { path });
+        await runExport('export_all_user_permissions', { path });
     }
     catch (error) {
         console.error(error);
@@ -0,0 +1,66 @@
+import { invoke } from "@tauri-apps/api";
+import type { OAuthClient, OAuthClientUpdate, Profile } from "./models";
+
+export const getProfiles = async (): Promise<Profile[]> => {
+    try {
+        let profiles: Profile[] = await invoke('get_profiles');
+        return profiles;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const addProfile = async (name: string, url: string): Promise<Profile> => {
+    try {
+        let profile: Profile = await invoke('add_profile', { name, url });
+        return profile;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const switchProfile = async (name: string): Promise<Profile> => {
+    try {
+        let profile: Profile = await invoke('switch_profile', { name });
+        return profile;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const removeProfile = async (name: string): Promise<void> => {
+    try {
+        await invoke('remove_profile', { name });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getOAuthClient = async (name: string): Promise<OAuthClient | null> => {
+    try {
+        let oauthClient: OAuthClient | null = await invoke('get_oauth_client', { name });
+        return oauthClient;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const setOAuthClient = async (name: string, oauthClient: OAuthClientUpdate | null): Promise<void> => {
+    try {
+        await invoke('set_oauth_client', { name, oauthClient });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
@@ -0,0 +1,19 @@
+export interface Profile {
+    name: string;
+    url: string;
+    isConnected: boolean;
+    isActive: boolean;
+}
+
+export interface OAuthClient {
+    clientId: string;
+    redirectUri?: string;
+    hasClientSecret: boolean;
+}
+
+// an empty client secret keeps the stored one
+export interface OAuthClientUpdate {
+    clientId: string;
+    clientSecret: string;
+    redirectUri?: string;
+}
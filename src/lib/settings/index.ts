@@ -0,0 +1,41 @@
+import { invoke } from "@tauri-apps/api";
+import { save } from "@tauri-apps/api/dialog";
+import { join } from "@tauri-apps/api/path";
+import type { AppSettings, SettingsUpdate } from "./models";
+
+export const getSettings = async (): Promise<AppSettings> => {
+    try {
+        let settings: AppSettings = await invoke('get_settings');
+        return settings;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const updateSettings = async (settings: AppSettings): Promise<SettingsUpdate> => {
+    try {
+        let update: SettingsUpdate = await invoke('update_settings', { settings });
+        return update;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+/** Opens the save dialog in the default export directory with the extension of the export format */
+export const selectExportPath = async (baseName: string): Promise<string | null> => {
+    const { export: exportSettings } = await getSettings();
+    const extension = exportSettings.format;
+    const fileName = `${baseName}.${extension}`;
+    const defaultPath = exportSettings.defaultDirectory
+        ? await join(exportSettings.defaultDirectory, fileName)
+        : fileName;
+
+    return save({
+        defaultPath,
+        filters: [{ name: extension.toUpperCase(), extensions: [extension] }]
+    });
+}
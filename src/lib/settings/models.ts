@@ -0,0 +1,55 @@
+import type { CredentialBackend } from "$lib/auth/models";
+import type { NetworkSettings } from "$lib/network/models";
+
+export type ExportFormat = 'csv' | 'json';
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface CacheSettings {
+    ttlSecs: number;
+    eventsTtlSecs: number;
+    staticTtlSecs: number;
+    maxEntries: number;
+}
+
+export interface ExportSettings {
+    pageSize: number;
+    concurrency: number;
+    maxRunningJobs: number;
+    permissionsConcurrency: number;
+    defaultDirectory?: string;
+    format: ExportFormat;
+}
+
+export type LogFormat = 'text' | 'json';
+
+export interface LogSettings {
+    level: LogLevel;
+    maxSizeMb: number;
+    maxFiles: number;
+    maxAgeDays: number;
+    compress: boolean;
+    format: LogFormat;
+    redaction: RedactionSettings;
+}
+
+export interface RedactionSettings {
+    emails: boolean;
+    secrets: boolean;
+    userNames: boolean;
+    nodeNames: boolean;
+    patterns: string[];
+}
+
+export interface AppSettings {
+    version: number;
+    cache: CacheSettings;
+    export: ExportSettings;
+    log: LogSettings;
+    network: NetworkSettings;
+    credentialBackend: CredentialBackend;
+}
+
+export interface SettingsUpdate {
+    settings: AppSettings;
+    restartRequired: boolean;
+}
@@ -0,0 +1,94 @@
+import { invoke } from "@tauri-apps/api";
+import type { ListParams } from "$lib/models";
+import type { UserList } from "$lib/users/models";
+import type { GroupList, GroupUserList } from "$lib/groups/models";
+import type { NodePermissionsListEntry } from "$lib/permissions/models";
+import type { EventList } from "$lib/events/models";
+import type { Snapshot, SnapshotParams } from "./models";
+
+export const takeSnapshot = async (params?: SnapshotParams): Promise<Snapshot> => {
+    try {
+        let snapshot: Snapshot = await invoke('take_snapshot', { params });
+        return snapshot;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const listSnapshots = async (): Promise<Snapshot[]> => {
+    try {
+        let snapshots: Snapshot[] = await invoke('list_snapshots');
+        return snapshots;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const deleteSnapshot = async (snapshotId: number): Promise<void> => {
+    try {
+        await invoke('delete_snapshot', { snapshotId });
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getSnapshotUsers = async (snapshotId: number, params: ListParams): Promise<UserList> => {
+    try {
+        let users: UserList = await invoke('get_snapshot_users', { snapshotId, params });
+        return users;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getSnapshotGroups = async (snapshotId: number, params: ListParams): Promise<GroupList> => {
+    try {
+        let groups: GroupList = await invoke('get_snapshot_groups', { snapshotId, params });
+        return groups;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getSnapshotGroupUsers = async (snapshotId: number, groupId: number, params: ListParams): Promise<GroupUserList> => {
+    try {
+        let groupUsers: GroupUserList = await invoke('get_snapshot_group_users', { snapshotId, groupId, params });
+        return groupUsers;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getSnapshotPermissions = async (snapshotId: number, params: ListParams): Promise<NodePermissionsListEntry[]> => {
+    try {
+        let permissions: NodePermissionsListEntry[] = await invoke('get_snapshot_permissions', { snapshotId, params });
+        return permissions;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
+
+export const getSnapshotEvents = async (snapshotId: number, params: ListParams): Promise<EventList> => {
+    try {
+        let events: EventList = await invoke('get_snapshot_events', { snapshotId, params });
+        return events;
+    }
+    catch (error) {
+        console.error(error);
+        throw error;
+    }
+}
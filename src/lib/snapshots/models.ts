@@ -0,0 +1,15 @@
+export interface Snapshot {
+    id: number;
+    profile: string;
+    url: string;
+    createdAt: string;
+    users: number;
+    groups: number;
+    groupUsers: number;
+    permissions: number;
+    events: number;
+}
+
+export interface SnapshotParams {
+    eventsDays?: number;
+}
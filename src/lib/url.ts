@@ -1,8 +1,8 @@
 import { invoke } from "@tauri-apps/api";
 
-export const validateUrl = async (url: string): Promise<boolean> => {
+export const validateUrl = async (url: string, profile?: string): Promise<boolean> => {
     const urlRegex = /^(https?:\/\/)([\w.-]+)(\.[\w.-]+)+([\/\w\.-]*)*\/?$/;
-    return urlRegex.test(url) && await checkDracoonUrl(url);
+    return urlRegex.test(url) && await checkDracoonUrl(url, profile);
 };
 
 export const addHttps = (url: string): string => {
@@ -17,6 +17,6 @@ export const addHttps = (url: string): string => {
     return `https://${url}`;
 };
 
-const checkDracoonUrl = async (url: string): Promise<boolean> => {
-    return invoke('validate_dracoon_url', { url });
+const checkDracoonUrl = async (url: string, profile?: string): Promise<boolean> => {
+    return invoke('validate_dracoon_url', { url, profile });
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 import { invoke } from "@tauri-apps/api";
+import { runExport } from "$lib/jobs";
 import type { UserList } from "./models";
 import type { ListParams } from "$lib/models";
 
-export const getUsers = async (params: ListParams): Promise<UserList> => {
+export const getUsers = async (params: ListParams, forceRefresh = false): Promise<UserList> => {
     try {
-        let users: UserList = await invoke('get_users', { params });
+        let users: UserList = await invoke('get_users', { params, forceRefresh });
 
         return users;
     }
@@ -16,7 +17,7 @@ export const getUsers = async (params: ListParams): Promise<UserList> => {
 
 export const downloadUsers = async (params: ListParams, path: string): Promise<void> => {
     try {
-        await invoke('export_users', { params, path });
+        await runExport('export_users', { params, path });
     }
     catch (error) {
         console.error(error);
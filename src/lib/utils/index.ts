@@ -1,5 +1,5 @@
 import { goto } from "$app/navigation";
-import type { ToastType } from "$lib/models";
+import type { AppError, ToastType } from "$lib/models";
 import type { ToastSettings } from "@skeletonlabs/skeleton";
 
 export const toReadableSize = (size: number): string => {
@@ -57,6 +57,19 @@ export const createToastSettings = (message: string, toastType: ToastType, timeo
     }
 }
 
+export const isAppError = (error: unknown, code?: string): error is AppError => {
+    const isError = typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+    return isError && (!code || (error as AppError).code === code);
+}
+
+export const formatError = (error: unknown): string => {
+    if (isAppError(error)) {
+        return error.message;
+    }
+
+    return `${error}`;
+}
+
 export const handleNodeNavigation = async (id: number, permissions?: boolean) => {
     if (permissions) {
         await goto(`/nodes/${id}/permissions`);